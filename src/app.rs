@@ -1,4 +1,26 @@
-use crate::library::{Book, Config, Library, LibrarySearchCriteria};
+use std::cell::{Ref, RefCell};
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+use ratatui::widgets::TableState;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::Palette;
+use crate::config::{Config, CONFIG_PATH};
+use crate::i18n::{self, t, tr, Language};
+use crate::input::TextInput;
+use crate::isbn::{Isbn, IsbnError};
+use crate::keymap::{Action, Key, KeyContext, Keymap};
+use crate::library::{Book, Genre, Library, LibrarySearchCriteria, Status};
+use crate::notifications::{Level, Notifications};
+use crate::tasks::{Outcome, Running, Task};
+use crate::theme::Theme;
 
 #[derive(Debug)]
 pub enum CurrentScreen {
@@ -6,98 +28,1462 @@ pub enum CurrentScreen {
     Home,
     NewOwner,
     Searching,
+    Filtering,
     CheckingOut,
-    CheckedOutResult,
     Exiting,
 }
 
+/// The views of the main screen, each with its own selection and scroll.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Tab {
+    Catalog,
+    Loans,
+    Patrons,
+    /// Charts about the collection and its loans.
+    Reports,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 4] = [Tab::Catalog, Tab::Loans, Tab::Patrons, Tab::Reports];
+}
+
+impl fmt::Display for Tab {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tab::Catalog => f.write_str(t("Catalog")),
+            Tab::Loans => f.write_str(t("Loans")),
+            Tab::Patrons => f.write_str(t("Patrons")),
+            Tab::Reports => f.write_str(t("Reports")),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Column {
+    Title,
+    Author,
+    Isbn,
+    Year,
+    Genre,
+    Status,
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Column::Title => f.write_str(t("Title")),
+            Column::Author => f.write_str(t("Author")),
+            Column::Isbn => f.write_str(t("ISBN")),
+            Column::Year => f.write_str(t("Year")),
+            Column::Genre => f.write_str(t("Genre")),
+            Column::Status => f.write_str(t("Status")),
+        }
+    }
+}
+
+impl Column {
+    /// Every column, in default display order; keys 1-6 follow this order.
+    pub const ALL: [Column; 6] = [
+        Column::Title,
+        Column::Author,
+        Column::Isbn,
+        Column::Year,
+        Column::Genre,
+        Column::Status,
+    ];
+
+    pub fn cell(&self, book: &Book) -> String {
+        match self {
+            Column::Title => book.get_title().to_string(),
+            Column::Author => book.get_author().to_string(),
+            Column::Isbn => book.get_isbn().to_string(),
+            Column::Year => match book.get_publication_year() {
+                0 => "-".to_string(),
+                year => year.to_string(),
+            },
+            Column::Genre => book.get_genre().to_string(),
+            Column::Status => book.get_status().to_string(),
+        }
+    }
+
+    fn compare(&self, a: &Book, b: &Book) -> Ordering {
+        match self {
+            Column::Year => a.get_publication_year().cmp(&b.get_publication_year()),
+            _ => self
+                .cell(a)
+                .to_lowercase()
+                .cmp(&self.cell(b).to_lowercase()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Catalogs larger than this wait for a pause in typing before searching.
+const SEARCH_DEBOUNCE_BOOKS: usize = 2000;
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Rows the mouse wheel moves per step.
+const SCROLL_LINES: isize = 3;
+
+/// Narrows the catalog; every field left as `None` lets all books through.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Filter {
+    pub genre: Option<Genre>,
+    pub status: Option<Status>,
+    pub year_from: Option<u16>,
+    pub year_to: Option<u16>,
+}
+
+impl Filter {
+    pub fn matches(&self, book: &Book) -> bool {
+        let year = book.get_publication_year();
+        self.genre.as_ref().is_none_or(|g| g == book.get_genre())
+            && self.status.as_ref().is_none_or(|s| s == book.get_status())
+            && self.year_from.is_none_or(|from| year >= from)
+            && self.year_to.is_none_or(|to| year <= to)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.genre.is_some()
+            || self.status.is_some()
+            || self.year_from.is_some()
+            || self.year_to.is_some()
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(genre) = &self.genre {
+            parts.push(tr!("Genre: {}", genre));
+        }
+        if let Some(status) = &self.status {
+            parts.push(tr!("Status: {}", status));
+        }
+        match (self.year_from, self.year_to) {
+            (Some(from), Some(to)) => parts.push(tr!("Year: {}-{}", from, to)),
+            (Some(from), None) => parts.push(tr!("Year: from {}", from)),
+            (None, Some(to)) => parts.push(tr!("Year: until {}", to)),
+            (None, None) => {}
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// What the catalog view was built from.
+#[derive(Debug, PartialEq, Clone)]
+struct CatalogKey {
+    revision: u64,
+    filter: Filter,
+    search: Option<(LibrarySearchCriteria, String)>,
+    sort: Option<(Column, SortOrder)>,
+    /// Genres and statuses sort by their translated names.
+    language: Language,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FilterField {
+    Genre,
+    Status,
+    YearFrom,
+    YearTo,
+}
+
+impl FilterField {
+    pub const ALL: [FilterField; 4] = [
+        FilterField::Genre,
+        FilterField::Status,
+        FilterField::YearFrom,
+        FilterField::YearTo,
+    ];
+}
+
+/// Steps through `options` and back to `None`, in either direction.
+fn cycle<T: PartialEq + Clone>(options: &[T], current: &Option<T>, forward: bool) -> Option<T> {
+    let position = current
+        .as_ref()
+        .and_then(|c| options.iter().position(|o| o == c));
+    let next = match (position, forward) {
+        (None, true) => Some(0),
+        (None, false) => Some(options.len() - 1),
+        (Some(i), true) if i + 1 < options.len() => Some(i + 1),
+        (Some(i), false) if i > 0 => Some(i - 1),
+        _ => None,
+    };
+    next.map(|i| options[i].clone())
+}
+
+/// Something an update leaves to the caller, so that updating the state
+/// never touches files, threads or the terminal.
+#[derive(Debug)]
+pub enum Effect {
+    /// Draw the screen again.
+    Render,
+    SaveConfig,
+    SaveLibrary,
+    /// Run a task on a worker thread.
+    Spawn(Task),
+    /// Close the app; `true` saves the library first.
+    Quit(bool),
+}
+
+/// Where unsaved changes to the library at `library_path` go when the app
+/// stops abnormally.
+pub fn recovery_path(library_path: &str) -> String {
+    format!("{}.recovery", library_path)
+}
+
+/// What a mouse event did.
+enum Click {
+    /// Changed the state itself.
+    Handled,
+    /// Stands for a key press.
+    Key(KeyEvent),
+}
+
 #[derive(Debug)]
 pub struct App {
     pub loaded: bool,
+    /// Effects requested by the update in progress.
+    effects: Vec<Effect>,
     pub current_screen: CurrentScreen,
     pub library: Option<Library>,
+    /// Set when the config file had problems, so saving over it would lose
+    /// what was written there.
+    pub config_read_only: bool,
+    /// Bumped whenever the books change, so the catalog view is rebuilt.
+    library_revision: u64,
+    /// The catalog view as indices into the library's books, with what it
+    /// was built from.
+    catalog: RefCell<Option<(CatalogKey, Vec<usize>)>>,
     pub config: Config,
+    pub keymap: Keymap,
+    pub theme: Theme,
     pub entering_owner: bool,
-    pub owner_input: String,
+    pub owner_input: TextInput,
     pub searching_criteria: LibrarySearchCriteria,
-    pub searching_input: String,
+    pub searching_input: TextInput,
+    /// The query the results are showing; lags `searching_input` while a
+    /// debounced search is pending.
+    pub search_query: String,
+    search_pending_since: Option<DateTime<Local>>,
     pub term_input_mode: bool,
     pub selected_book: Option<Book>,
-    pub checkout_success: Option<Result<(), String>>,
-    pub error_message: Option<String>,
+    /// Who checkouts and holds are for; the owner when `None`.
+    pub current_patron: Option<String>,
+    pub tab: Tab,
+    /// Selection in the Catalog tab, and in the search and filter screens.
+    pub home_state: TableState,
+    pub loans_state: TableState,
+    pub patrons_state: TableState,
+    /// Where each tab's label was drawn, for clicking.
+    pub tab_areas: Vec<(Rect, Tab)>,
+    /// Rows visible in the Home table, updated on every render.
+    pub home_page_size: usize,
+    /// Where the Home table rows were drawn, empty when it isn't shown.
+    pub home_rows_area: Rect,
+    /// Areas from the last render that act like a key when clicked, such as
+    /// key hints and popup buttons.
+    pub click_targets: Vec<(Rect, Key)>,
+    pub help_open: bool,
+    pub palette: Option<Palette>,
+    pub notifications: Notifications,
+    /// The task running on a worker thread, if any.
+    pub task: Option<Running>,
+    /// Ticks since the app started, for animations.
+    pub ticks: usize,
+    /// When the library was first changed after it was last saved.
+    pub unsaved_since: Option<DateTime<Local>>,
+    pub history_open: bool,
+    /// First history row shown; clamped to the history length on render.
+    pub history_scroll: usize,
+    /// History rows visible, updated on every render.
+    pub history_page_size: usize,
+    /// First help row shown; clamped to the table length on render.
+    pub help_scroll: usize,
+    /// Help rows visible, updated on every render.
+    pub help_page_size: usize,
+    /// While set, the column keys show/hide columns instead of sorting.
+    pub column_mode: bool,
+    pub filter: Filter,
+    pub filter_field: FilterField,
+    pub year_from_input: TextInput,
+    pub year_to_input: TextInput,
+    /// The time to run at instead of the current one, for tests; every
+    /// timestamp, timeout and due date is read through `now`.
+    pub clock: Option<DateTime<Local>>,
 }
 
 impl App {
+    /// An app with the settings in `config.json`. If the file has problems
+    /// they're shown once it starts, and the file is left as it is.
     pub fn new() -> Self {
-        let config = Config::new("library.json");
+        let (config, problems) = match Config::load(CONFIG_PATH) {
+            Ok((config, warnings)) => {
+                let problems = warnings.into_iter().map(|w| (Level::Warning, w));
+                (config, problems.collect())
+            }
+            Err(e) => {
+                let text = tr!("Couldn't read {}: {}", CONFIG_PATH, e);
+                (Config::default(), vec![(Level::Error, text)])
+            }
+        };
+        let mut app = App::with_config(config);
+        if !problems.is_empty() {
+            for (level, text) in problems {
+                app.notify(level, text);
+            }
+            let text = tr!("Settings won't be saved until {} is fixed", CONFIG_PATH);
+            app.notify(Level::Warning, text);
+            app.config_read_only = true;
+        }
+        app
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        let keymap = Keymap::new(config.keymap, &config.key_bindings);
+        i18n::set_language(config.language.unwrap_or_else(Language::from_env));
         App {
+            keymap,
+            theme: Theme::load(config.theme),
             loaded: false,
+            effects: Vec::new(),
             current_screen: CurrentScreen::Loading,
             library: None,
+            config_read_only: false,
+            library_revision: 0,
+            catalog: RefCell::new(None),
             entering_owner: false,
-            owner_input: String::new(),
+            owner_input: TextInput::new(),
             config,
             searching_criteria: LibrarySearchCriteria::Title,
-            searching_input: String::new(),
+            searching_input: TextInput::new(),
+            search_query: String::new(),
+            search_pending_since: None,
             term_input_mode: false,
-            checkout_success: None,
-            error_message: None,
             selected_book: None,
+            current_patron: None,
+            tab: Tab::Catalog,
+            home_state: TableState::default().with_selected(Some(0)),
+            loans_state: TableState::default().with_selected(Some(0)),
+            patrons_state: TableState::default().with_selected(Some(0)),
+            tab_areas: Vec::new(),
+            home_page_size: 1,
+            home_rows_area: Rect::default(),
+            click_targets: Vec::new(),
+            help_open: false,
+            palette: None,
+            notifications: Notifications::default(),
+            task: None,
+            ticks: 0,
+            unsaved_since: None,
+            history_open: false,
+            history_scroll: 0,
+            history_page_size: 1,
+            help_scroll: 0,
+            help_page_size: 1,
+            column_mode: false,
+            filter: Filter::default(),
+            filter_field: FilterField::Genre,
+            year_from_input: TextInput::new(),
+            year_to_input: TextInput::new(),
+            clock: None,
         }
     }
 
+    pub fn now(&self) -> DateTime<Local> {
+        self.clock.unwrap_or_else(Local::now)
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+
+    /// Time passed since `since`; zero if the clock went back.
+    fn elapsed(&self, since: DateTime<Local>) -> Duration {
+        (self.now() - since).to_std().unwrap_or_default()
+    }
+
     fn loaded(&mut self) {
         self.loaded = true
     }
 
-    pub fn load(&mut self) {
-        let lib = Library::from_file(&self.config.library_path);
-        self.library = match lib {
-            Some(l) => {
+    /// Asks the caller of the current update for `effect`.
+    pub fn request(&mut self, effect: Effect) {
+        self.effects.push(effect);
+    }
+
+    /// The effects requested so far, with a render for the changed state.
+    fn take_effects(&mut self) -> Vec<Effect> {
+        let mut effects = std::mem::take(&mut self.effects);
+        effects.push(Effect::Render);
+        effects
+    }
+
+    /// Runs `task` on a worker thread, unless another one is running.
+    pub fn start_task(&mut self, task: Task) {
+        match &self.task {
+            Some(running) => {
+                let text = tr!("Wait until this is done: {}", running.label);
+                self.notify(Level::Warning, text);
+            }
+            None => self.task = Some(task.spawn()),
+        }
+    }
+
+    /// Stops waiting for the running task. Its worker finishes on its own and
+    /// what it sends back is dropped with the receiver.
+    pub fn cancel_task(&mut self) {
+        if let Some(running) = self.task.take() {
+            self.notify(Level::Info, tr!("Cancelled: {}", running.label));
+        }
+    }
+
+    fn finish_task(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Loaded(Ok(Some(library)), restored) => {
+                self.set_library(Some(library));
                 self.loaded();
-                Some(l)
+                self.current_screen = CurrentScreen::Home;
+                if restored {
+                    self.restored();
+                }
             }
-            None => {
+            Outcome::Loaded(Ok(None), _) => {
                 self.entering_owner = true;
-                None
+                self.current_screen = CurrentScreen::NewOwner;
+            }
+            // Stay on the loading screen, where the file can be fixed and
+            // loaded again.
+            Outcome::Loaded(Err(e), _) => self.notify(Level::Error, e),
+            Outcome::Read(result) => {
+                let result = result.map(|read| self.merge_import(read));
+                self.notify_result(result, Level::Error);
+            }
+            Outcome::Opened(path, result, restored) => {
+                let result = result.and_then(|library| self.switch_library(&path, library));
+                let opened = result.is_ok();
+                self.notify_result(result, Level::Error);
+                if opened && restored {
+                    self.restored();
+                }
+            }
+            Outcome::Exported(result) => self.notify_result(result, Level::Error),
+            Outcome::Crashed(message) => {
+                self.notify(Level::Error, tr!("The task failed: {}", message))
             }
+        }
+    }
+
+    /// Marks a library read from the recovery file as unsaved, so the next
+    /// save moves the changes into the library file.
+    fn restored(&mut self) {
+        self.changed();
+        let text = tr!(
+            "Restored unsaved changes from {}",
+            recovery_path(&self.config.library_path)
+        );
+        self.notify(Level::Warning, text);
+    }
+
+    /// Writes the library to the recovery file if it has unsaved changes,
+    /// returning the file's path.
+    pub fn save_recovery(&self) -> Option<Result<String, io::Error>> {
+        self.unsaved_since?;
+        let path = recovery_path(&self.config.library_path);
+        Some(self.library.as_ref()?.save(&path).map(|_| path))
+    }
+
+    /// Marks the library as changed since it was last saved.
+    pub fn changed(&mut self) {
+        self.library_revision += 1;
+        let now = self.now();
+        self.unsaved_since.get_or_insert(now);
+    }
+
+    /// Replaces the library, e.g. after loading or switching files.
+    pub fn set_library(&mut self, library: Option<Library>) {
+        self.library = library;
+        self.library_revision += 1;
+    }
+
+    /// Writes the library to its file, trying again at the next autosave if
+    /// that fails.
+    pub fn save_library(&mut self) {
+        if let Err(e) = self.write_library() {
+            self.unsaved_since = Some(self.now());
+            self.notify(Level::Error, tr!("Couldn't save library: {}", e));
+        }
+    }
+
+    /// Writes the library to its file and removes the recovery file, whose
+    /// changes are in the library file now.
+    pub fn write_library(&mut self) -> Result<(), io::Error> {
+        let Some(library) = &self.library else {
+            return Ok(());
+        };
+        library.save(&self.config.library_path)?;
+        self.unsaved_since = None;
+        match fs::remove_file(recovery_path(&self.config.library_path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn search_active(&self) -> bool {
+        matches!(self.current_screen, CurrentScreen::Searching) && !self.search_query.is_empty()
+    }
+
+    /// Books passing the filter (and the search, while searching), in the
+    /// order the Home table shows them.
+    pub fn catalog_books(&self) -> Vec<&Book> {
+        let Some(library) = self.library.as_ref() else {
+            return Vec::new();
+        };
+        let books = library.get_books();
+        self.catalog_view().iter().map(|&i| &books[i]).collect()
+    }
+
+    /// Indices of `catalog_books` into the library's books. Only rebuilt
+    /// when the books, the filter, the search or the sort changed.
+    fn catalog_view(&self) -> Ref<'_, [usize]> {
+        let key = CatalogKey {
+            revision: self.library_revision,
+            filter: self.filter.clone(),
+            search: match self.search_active() {
+                true => Some((self.searching_criteria.clone(), self.search_query.clone())),
+                false => None,
+            },
+            sort: self.config.sort,
+            language: i18n::language(),
+        };
+        let stale = self
+            .catalog
+            .borrow()
+            .as_ref()
+            .is_none_or(|(built_from, _)| *built_from != key);
+        if stale {
+            let view = self.build_catalog_view(&key);
+            self.catalog.replace(Some((key, view)));
+        }
+        Ref::map(self.catalog.borrow(), |catalog| {
+            catalog.as_ref().unwrap().1.as_slice()
+        })
+    }
+
+    fn build_catalog_view(&self, key: &CatalogKey) -> Vec<usize> {
+        let Some(library) = self.library.as_ref() else {
+            return Vec::new();
+        };
+        let books = library.get_books();
+        let mut view: Vec<usize> = (0..books.len())
+            .filter(|&i| key.filter.matches(&books[i]))
+            .filter(|&i| {
+                key.search
+                    .as_ref()
+                    .is_none_or(|(criteria, query)| criteria.matches(&books[i], query))
+            })
+            .collect();
+        if let Some((column, order)) = key.sort {
+            view.sort_by(|&a, &b| match order {
+                SortOrder::Ascending => column.compare(&books[a], &books[b]),
+                SortOrder::Descending => column.compare(&books[b], &books[a]),
+            });
+        }
+        view
+    }
+
+    /// Rows in the current tab's table.
+    fn row_count(&self) -> usize {
+        let Some(library) = self.library.as_ref() else {
+            return 0;
         };
+        match self.tab {
+            Tab::Catalog => self.catalog_view().len(),
+            Tab::Loans => library.active_loans().len(),
+            Tab::Patrons => library.patrons().len(),
+            Tab::Reports => 0,
+        }
+    }
+
+    /// The selection of the current tab's table.
+    pub fn table_state(&mut self) -> &mut TableState {
+        match self.tab {
+            Tab::Loans => &mut self.loans_state,
+            Tab::Patrons => &mut self.patrons_state,
+            Tab::Catalog | Tab::Reports => &mut self.home_state,
+        }
+    }
+
+    /// Moves the Home cursor by `delta` rows, stopping at either end.
+    pub fn move_cursor(&mut self, delta: isize) {
+        let count = self.row_count();
+        let state = self.table_state();
+        if count == 0 {
+            state.select(None);
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, count as isize - 1);
+        state.select(Some(next as usize));
+    }
+
+    fn select_tab(&mut self, tab: Tab) {
+        self.tab = tab;
+        // The list may have changed since the tab was last shown.
+        self.move_cursor(0);
+    }
+
+    /// Moves `steps` tabs along, wrapping around at either end.
+    fn switch_tab(&mut self, steps: isize) {
+        let count = Tab::ALL.len() as isize;
+        let current = Tab::ALL.iter().position(|tab| *tab == self.tab).unwrap() as isize;
+        self.select_tab(Tab::ALL[(current + steps).rem_euclid(count) as usize]);
+    }
+
+    /// Handles scrolling and row clicks in the Home table. Clicks on a key
+    /// hint or button are handled as the key they stand for; outside the
+    /// filter bar, a click on the row that is already selected opens it.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) -> Vec<Effect> {
+        match self.click(mouse) {
+            Some(Click::Key(key)) => self.handle_key(key),
+            Some(Click::Handled) => self.take_effects(),
+            None => Vec::new(),
+        }
+    }
+
+    /// What a mouse event did: nothing, changed the state itself, or
+    /// stands for a key.
+    fn click(&mut self, mouse: MouseEvent) -> Option<Click> {
+        let position = Position::new(mouse.column, mouse.row);
+        let over_rows = self.home_rows_area.contains(position);
+        match mouse.kind {
+            MouseEventKind::ScrollDown if self.help_open => self.scroll_help(SCROLL_LINES),
+            MouseEventKind::ScrollUp if self.help_open => self.scroll_help(-SCROLL_LINES),
+            MouseEventKind::ScrollDown if over_rows => self.move_cursor(SCROLL_LINES),
+            MouseEventKind::ScrollUp if over_rows => self.move_cursor(-SCROLL_LINES),
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some((_, key)) = self
+                    .click_targets
+                    .iter()
+                    .find(|(area, _)| area.contains(position))
+                {
+                    return Some(Click::Key(key.to_event()));
+                }
+                if let Some((_, tab)) = self
+                    .tab_areas
+                    .iter()
+                    .find(|(area, _)| area.contains(position))
+                {
+                    self.select_tab(*tab);
+                    return Some(Click::Handled);
+                }
+                if over_rows {
+                    let count = self.row_count();
+                    let opens = !matches!(self.current_screen, CurrentScreen::Filtering);
+                    let top = self.home_rows_area.y;
+                    let state = self.table_state();
+                    let row = state.offset() + (position.y - top) as usize;
+                    if row < count {
+                        if opens && state.selected() == Some(row) {
+                            return self
+                                .keymap
+                                .key(Action::Confirm)
+                                .map(|key| Click::Key(key.to_event()));
+                        }
+                        state.select(Some(row));
+                        return Some(Click::Handled);
+                    }
+                }
+                return None;
+            }
+            _ => return None,
+        }
+        Some(Click::Handled)
+    }
+
+    pub fn move_cursor_page(&mut self, pages: isize) {
+        self.move_cursor(pages * self.home_page_size.max(1) as isize);
+    }
+
+    pub fn move_cursor_to_start(&mut self) {
+        self.move_cursor(isize::MIN / 2);
+    }
+
+    pub fn move_cursor_to_end(&mut self) {
+        self.move_cursor(isize::MAX / 2);
+    }
+
+    /// Selects the book under the cursor for checking out.
+    pub fn open_selected(&mut self) {
+        self.selected_book = self.highlighted_book().cloned();
+    }
+
+    /// Writes the config file.
+    pub fn save_config(&mut self) {
+        if self.config_read_only {
+            return;
+        }
+        if let Err(e) = self.config.save(CONFIG_PATH) {
+            self.notify(Level::Error, tr!("Couldn't save config: {}", e));
+        }
+    }
+
+    /// Sorts the catalog by `column`, flipping the order if it already is.
+    pub fn sort_by(&mut self, column: Column) {
+        self.config.sort = match self.config.sort {
+            Some((current, SortOrder::Ascending)) if current == column => {
+                Some((column, SortOrder::Descending))
+            }
+            _ => Some((column, SortOrder::Ascending)),
+        };
+        self.request(Effect::SaveConfig);
+    }
+
+    /// Shows or hides `column`, always keeping at least one visible.
+    pub fn toggle_column(&mut self, column: Column) {
+        let columns = &mut self.config.columns;
+        match columns.iter().position(|c| *c == column) {
+            Some(i) if columns.len() > 1 => {
+                columns.remove(i);
+            }
+            Some(_) => return,
+            None => {
+                // Keep the default relative order when re-showing a column.
+                columns.push(column);
+                columns.sort_by_key(|c| Column::ALL.iter().position(|a| a == c));
+            }
+        }
+        self.request(Effect::SaveConfig);
     }
 
+    pub fn start_search(&mut self) {
+        self.current_screen = CurrentScreen::Searching;
+        self.term_input_mode = true;
+        self.searching_input.clear();
+        self.apply_search();
+    }
+
+    /// Refreshes the results after the query changed, right away for small
+    /// catalogs and after a pause in typing for large ones.
+    pub fn search_input_changed(&mut self) {
+        let size = self.library.as_ref().map_or(0, |l| l.get_books().len());
+        if size > SEARCH_DEBOUNCE_BOOKS {
+            self.search_pending_since = Some(self.now());
+        } else {
+            self.apply_search();
+        }
+    }
+
+    /// How long until a pending search should run, if one is pending.
+    pub fn search_timeout(&self) -> Option<Duration> {
+        self.search_pending_since
+            .map(|since| SEARCH_DEBOUNCE.saturating_sub(self.elapsed(since)))
+    }
+
+    /// Does whatever is due: a debounced search, taking down toasts,
+    /// collecting a finished task and autosaving.
+    pub fn tick(&mut self) -> Vec<Effect> {
+        self.ticks = self.ticks.wrapping_add(1);
+        if self
+            .search_timeout()
+            .is_some_and(|timeout| timeout.is_zero())
+        {
+            self.apply_search();
+        }
+        let now = self.now();
+        self.notifications.expire(now);
+        if let Some(outcome) = self.task.as_mut().and_then(Running::poll) {
+            self.task = None;
+            self.finish_task(outcome);
+        }
+        let autosave = Duration::from_secs(self.config.autosave_minutes * 60);
+        if self.config.autosave_minutes > 0
+            && self
+                .unsaved_since
+                .is_some_and(|since| self.elapsed(since) >= autosave)
+        {
+            self.request(Effect::SaveLibrary);
+        }
+        self.take_effects()
+    }
+
+    /// Runs the search for the current input, moving the cursor to the
+    /// first result.
     pub fn apply_search(&mut self) {
-        if self.searching_input.is_empty() {
+        self.search_pending_since = None;
+        if self.search_query != self.searching_input.value() {
+            self.search_query = self.searching_input.value().to_string();
+            self.home_state.select(Some(0));
+        }
+        self.move_cursor(0);
+    }
+
+    pub fn next_filter_field(&mut self, forward: bool) {
+        let fields = FilterField::ALL;
+        let i = fields.iter().position(|f| *f == self.filter_field).unwrap();
+        let next = match forward {
+            true => (i + 1) % fields.len(),
+            false => (i + fields.len() - 1) % fields.len(),
+        };
+        self.filter_field = fields[next];
+    }
+
+    /// Changes the genre or status filter under the cursor.
+    pub fn cycle_filter_value(&mut self, forward: bool) {
+        match self.filter_field {
+            FilterField::Genre => {
+                self.filter.genre = cycle(&Genre::ALL, &self.filter.genre, forward)
+            }
+            FilterField::Status => {
+                self.filter.status = cycle(&Status::ALL, &self.filter.status, forward)
+            }
+            _ => {}
+        }
+        self.move_cursor(0);
+    }
+
+    /// The year field under the cursor, if the cursor is on one.
+    fn focused_year_input(&mut self) -> Option<&mut TextInput> {
+        match self.filter_field {
+            FilterField::YearFrom => Some(&mut self.year_from_input),
+            FilterField::YearTo => Some(&mut self.year_to_input),
+            _ => None,
+        }
+    }
+
+    /// Edits the year field under the cursor, which takes up to 4 digits.
+    /// Returns whether the key was used.
+    fn edit_filter_year(&mut self, key: KeyEvent) -> bool {
+        let Some(input) = self.focused_year_input() else {
+            return false;
+        };
+        if let KeyCode::Char(c) = key.code {
+            if !c.is_ascii_digit() || input.value().len() >= 4 {
+                return false;
+            }
+        }
+        if !input.handle_key(key) {
+            return false;
+        }
+        self.year_filter_changed();
+        true
+    }
+
+    fn year_filter_changed(&mut self) {
+        self.filter.year_from = self.year_from_input.value().parse().ok();
+        self.filter.year_to = self.year_to_input.value().parse().ok();
+        self.move_cursor(0);
+    }
+
+    /// Edits the search query; returns whether the key was used.
+    fn edit_search(&mut self, key: KeyEvent) -> bool {
+        if !self.searching_input.handle_key(key) {
+            return false;
+        }
+        self.search_input_changed();
+        true
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.help_open = !self.help_open;
+        self.help_scroll = 0;
+    }
+
+    /// Scrolls the help overlay by `delta` rows.
+    pub fn scroll_help(&mut self, delta: isize) {
+        self.help_scroll = self.help_scroll.saturating_add_signed(delta);
+    }
+
+    pub fn scroll_history(&mut self, delta: isize) {
+        self.history_scroll = self.history_scroll.saturating_add_signed(delta);
+    }
+
+    /// Which set of key bindings applies right now.
+    pub fn key_context(&self) -> KeyContext {
+        if self.help_open {
+            KeyContext::Help
+        } else if self.history_open {
+            KeyContext::Messages
+        } else if self.palette.is_some() {
+            KeyContext::Palette
+        } else {
+            self.screen_context()
+        }
+    }
+
+    /// The key context of the current screen, ignoring the help overlay.
+    pub fn screen_context(&self) -> KeyContext {
+        match self.current_screen {
+            CurrentScreen::Loading => KeyContext::Loading,
+            CurrentScreen::NewOwner => KeyContext::NewOwner,
+            CurrentScreen::Home if self.column_mode => KeyContext::Columns,
+            CurrentScreen::Home => match self.tab {
+                Tab::Catalog => KeyContext::Home,
+                Tab::Loans => KeyContext::Loans,
+                Tab::Patrons => KeyContext::Patrons,
+                Tab::Reports => KeyContext::Reports,
+            },
+            CurrentScreen::Searching if self.term_input_mode => KeyContext::SearchInput,
+            CurrentScreen::Searching => KeyContext::SearchCriteria,
+            CurrentScreen::Filtering => KeyContext::Filtering,
+            CurrentScreen::CheckingOut => KeyContext::Book,
+            CurrentScreen::Exiting => KeyContext::Exiting,
+        }
+    }
+
+    /// Passes a key to whichever field is being edited. Returns whether the
+    /// key was used, so screen shortcuts only see the keys left over.
+    pub fn edit_text(&mut self, key: KeyEvent) -> bool {
+        if self.help_open || self.history_open {
+            return false;
+        }
+        if let Some(palette) = self.palette.as_mut() {
+            if !palette.input().handle_key(key) {
+                return false;
+            }
+            palette.selected = 0;
+            return true;
+        }
+        match self.current_screen {
+            CurrentScreen::NewOwner => self.owner_input.handle_key(key),
+            CurrentScreen::Searching if self.term_input_mode => self.edit_search(key),
+            CurrentScreen::Filtering => self.edit_filter_year(key),
+            _ => false,
+        }
+    }
+
+    /// Inserts pasted text into whichever field is being edited.
+    pub fn paste(&mut self, text: &str) -> Vec<Effect> {
+        self.insert_pasted(text);
+        self.take_effects()
+    }
+
+    fn insert_pasted(&mut self, text: &str) {
+        if self.help_open || self.history_open {
             return;
         }
-        self.selected_book = match self
-            .library
-            .as_ref()
-            .unwrap()
-            .search_by(&self.searching_criteria, &self.searching_input)
+        if let Some(palette) = self.palette.as_mut() {
+            palette.input().insert_str(text);
+            palette.selected = 0;
+            return;
+        }
+        match self.current_screen {
+            CurrentScreen::NewOwner => self.owner_input.insert_str(text),
+            CurrentScreen::Searching if self.term_input_mode => {
+                self.searching_input.insert_str(text);
+                self.search_input_changed();
+            }
+            CurrentScreen::Filtering => {
+                let Some(input) = self.focused_year_input() else {
+                    return;
+                };
+                let room = 4 - input.value().len();
+                let digits: String = text
+                    .chars()
+                    .filter(char::is_ascii_digit)
+                    .take(room)
+                    .collect();
+                input.insert_str(&digits);
+                self.year_filter_changed();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.filter = Filter::default();
+        self.year_from_input.clear();
+        self.year_to_input.clear();
+        self.move_cursor(0);
+    }
+
+    /// Why the search input can never match: a complete ISBN whose check
+    /// digit is wrong. Partial ISBNs still narrow the results while typing.
+    pub fn search_input_error(&self) -> Option<IsbnError> {
+        match self.searching_criteria {
+            LibrarySearchCriteria::ISBN => match Isbn::parse(self.searching_input.value()) {
+                Err(IsbnError::InvalidChecksum) => Some(IsbnError::InvalidChecksum),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Handles a key press: text fields first, then the column keys, then
+    /// the key bindings. Keys that do nothing have no effects.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Vec<Effect> {
+        if key.kind == KeyEventKind::Release {
+            return Vec::new();
+        }
+        // The field being typed into gets the first look at every key.
+        if self.edit_text(key) {
+            return self.take_effects();
+        }
+        // The column keys are positions in the column list, not actions.
+        let context = self.key_context();
+        if let (KeyContext::Home | KeyContext::Columns, KeyCode::Char(c @ '1'..='6')) =
+            (context, key.code)
         {
-            Some(book) => Some(book.clone()),
-            None => None,
+            let column = Column::ALL[c.to_digit(10).unwrap() as usize - 1];
+            match context {
+                KeyContext::Home => self.sort_by(column),
+                _ => self.toggle_column(column),
+            }
+            return self.take_effects();
+        }
+        match self.keymap.action(context, key) {
+            Some(action) => self.update(action),
+            None => Vec::new(),
+        }
+    }
+
+    /// Updates the state for `action`, returning what's left for the caller
+    /// to do. Every key binding, key hint, button and palette command ends
+    /// up here.
+    pub fn update(&mut self, action: Action) -> Vec<Effect> {
+        self.perform(action);
+        self.take_effects()
+    }
+
+    /// Runs `action` for the current screen, requesting its effects.
+    pub fn perform(&mut self, action: Action) {
+        if self.help_open {
+            let page = self.help_page_size.max(1) as isize;
+            match action {
+                Action::Help | Action::Back => self.toggle_help(),
+                Action::MoveUp => self.scroll_help(-1),
+                Action::MoveDown => self.scroll_help(1),
+                Action::PageUp => self.scroll_help(-page),
+                Action::PageDown => self.scroll_help(page),
+                Action::First => self.help_scroll = 0,
+                Action::Last => self.help_scroll = usize::MAX,
+                _ => {}
+            }
+            return;
+        }
+        if action == Action::Help {
+            self.toggle_help();
+            return;
+        }
+        if self.history_open {
+            let page = self.history_page_size.max(1) as isize;
+            match action {
+                Action::Messages | Action::Back => self.history_open = false,
+                Action::MoveUp => self.scroll_history(-1),
+                Action::MoveDown => self.scroll_history(1),
+                Action::PageUp => self.scroll_history(-page),
+                Action::PageDown => self.scroll_history(page),
+                Action::First => self.history_scroll = 0,
+                Action::Last => self.history_scroll = usize::MAX,
+                _ => {}
+            }
+            return;
+        }
+        if action == Action::Messages {
+            self.history_open = true;
+            self.history_scroll = 0;
+            return;
+        }
+        if self.palette.is_some() {
+            self.perform_in_palette(action);
+            return;
+        }
+        if action == Action::Palette {
+            self.palette = Some(Palette::default());
+            return;
+        }
+        match self.current_screen {
+            CurrentScreen::Loading => match action {
+                Action::Back if self.task.is_some() => self.cancel_task(),
+                Action::Back => self.request(Effect::Quit(false)),
+                Action::Confirm if self.task.is_none() => {
+                    let path = self.config.library_path.clone();
+                    self.request(Effect::Spawn(Task::Load(path)));
+                }
+                _ => {}
+            },
+            CurrentScreen::NewOwner => {
+                if action == Action::Confirm {
+                    self.initialize_demo();
+                    self.current_screen = CurrentScreen::Home;
+                }
+            }
+            CurrentScreen::Home if self.column_mode => {
+                if let Action::Columns | Action::Back = action {
+                    self.column_mode = false;
+                }
+            }
+            CurrentScreen::Home if self.tab != Tab::Catalog => match action {
+                Action::NextTab => self.switch_tab(1),
+                Action::PrevTab => self.switch_tab(-1),
+                Action::Back | Action::Dashboard => self.select_tab(Tab::Catalog),
+                Action::Quit => self.current_screen = CurrentScreen::Exiting,
+                Action::MoveUp => self.move_cursor(-1),
+                Action::MoveDown => self.move_cursor(1),
+                Action::PageUp => self.move_cursor_page(-1),
+                Action::PageDown => self.move_cursor_page(1),
+                Action::First => self.move_cursor_to_start(),
+                Action::Last => self.move_cursor_to_end(),
+                Action::Confirm if self.tab == Tab::Patrons => {
+                    let library = self.library.as_ref().unwrap();
+                    let name = self
+                        .patrons_state
+                        .selected()
+                        .and_then(|i| library.patrons().get(i).map(|p| p.name.to_string()));
+                    if let Some(name) = name {
+                        self.set_patron(&name);
+                    }
+                }
+                Action::Confirm => {
+                    self.open_selected();
+                    if self.selected_book.is_some() {
+                        self.current_screen = CurrentScreen::CheckingOut;
+                    }
+                }
+                Action::ReturnBook => {
+                    self.open_selected();
+                    if self.selected_book.is_some() {
+                        self.return_book();
+                    }
+                }
+                _ => {}
+            },
+            CurrentScreen::Home => match action {
+                Action::Columns => self.column_mode = true,
+                Action::Filter => self.current_screen = CurrentScreen::Filtering,
+                Action::Dashboard => self.select_tab(Tab::Reports),
+                Action::NextTab => self.switch_tab(1),
+                Action::PrevTab => self.switch_tab(-1),
+                Action::Quit => {
+                    self.current_screen = CurrentScreen::Exiting;
+                }
+                Action::Search => self.start_search(),
+                Action::MoveUp => self.move_cursor(-1),
+                Action::MoveDown => self.move_cursor(1),
+                Action::PageUp => self.move_cursor_page(-1),
+                Action::PageDown => self.move_cursor_page(1),
+                Action::First => self.move_cursor_to_start(),
+                Action::Last => self.move_cursor_to_end(),
+                Action::Confirm => {
+                    self.open_selected();
+                    if self.selected_book.is_some() {
+                        self.current_screen = CurrentScreen::CheckingOut;
+                    }
+                }
+                _ => {}
+            },
+            CurrentScreen::Searching => match action {
+                Action::ToggleSearchInput => {
+                    self.term_input_mode = !self.term_input_mode;
+                }
+                Action::SearchByTitle | Action::SearchByAuthor | Action::SearchByIsbn => {
+                    self.searching_criteria = match action {
+                        Action::SearchByTitle => LibrarySearchCriteria::Title,
+                        Action::SearchByAuthor => LibrarySearchCriteria::Author,
+                        _ => LibrarySearchCriteria::ISBN,
+                    };
+                    self.move_cursor(0);
+                }
+                Action::Quit => self.current_screen = CurrentScreen::Exiting,
+                Action::MoveUp => self.move_cursor(-1),
+                Action::MoveDown => self.move_cursor(1),
+                Action::PageUp => self.move_cursor_page(-1),
+                Action::PageDown => self.move_cursor_page(1),
+                Action::Confirm => {
+                    self.apply_search();
+                    self.open_selected();
+                    if self.selected_book.is_some() {
+                        self.current_screen = CurrentScreen::CheckingOut;
+                    }
+                }
+                Action::Back => {
+                    self.current_screen = CurrentScreen::Home;
+                }
+                _ => {}
+            },
+            CurrentScreen::Filtering => match action {
+                Action::NextField => self.next_filter_field(true),
+                Action::PrevField => self.next_filter_field(false),
+                Action::NextValue => self.cycle_filter_value(true),
+                Action::PrevValue => self.cycle_filter_value(false),
+                Action::ClearFilter => self.clear_filter(),
+                Action::Confirm | Action::Back | Action::Filter => {
+                    self.current_screen = CurrentScreen::Home;
+                }
+                _ => {}
+            },
+            CurrentScreen::CheckingOut => match action {
+                Action::Confirm => self.check_out(),
+                Action::Quit => {
+                    self.current_screen = CurrentScreen::Exiting;
+                }
+                Action::ReturnBook => self.return_book(),
+                Action::PlaceHold => self.place_hold(),
+                Action::BackToSearch => {
+                    self.tab = Tab::Catalog;
+                    self.current_screen = CurrentScreen::Searching;
+                }
+                Action::Back => {
+                    self.current_screen = CurrentScreen::Home;
+                }
+                _ => {}
+            },
+            CurrentScreen::Exiting => match action {
+                Action::Yes => self.request(Effect::Quit(true)),
+                Action::No => {
+                    self.current_screen = CurrentScreen::Home;
+                }
+                _ => {}
+            },
         }
     }
 
+    /// Who checkouts and holds are recorded for.
+    pub fn patron(&self) -> String {
+        match (&self.current_patron, self.library.as_ref()) {
+            (Some(patron), _) => patron.clone(),
+            (None, Some(library)) => library.get_owner().to_string(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Records checkouts and holds for `name` from now on, or for the owner
+    /// when it's empty.
+    pub fn set_patron(&mut self, name: &str) {
+        self.current_patron = match name.trim() {
+            "" => None,
+            name => Some(name.to_string()),
+        };
+        let text = tr!("Checkouts and holds are now for {}", self.patron());
+        self.notify(Level::Info, text);
+    }
+
     pub fn check_out(&mut self) {
-        self.checkout_success = match self
+        let patron = self.patron();
+        let book = self.selected_book.as_ref().unwrap();
+        let (isbn, title) = (book.get_isbn().clone(), book.get_title().to_string());
+        let today = self.today();
+        let result = self
             .library
             .as_mut()
             .unwrap()
-            .check_out(self.selected_book.as_ref().unwrap().get_isbn())
-        {
-            Ok(_) => Some(Ok(())),
-            Err(e) => Some(Err(e.to_string())),
-        };
+            .check_out(&isbn, &patron, today);
+        if result.is_ok() {
+            self.changed();
+        }
+        self.notify_result(
+            result.map(|_| tr!("Checked out \"{}\"", title)),
+            Level::Warning,
+        );
     }
 
-    pub fn initialize_demo(&mut self) {
-        self.library = Some(Library::initialize_demo(&self.owner_input));
-        self.library
-            .as_ref()
+    pub fn return_book(&mut self) {
+        let book = self.selected_book.as_ref().unwrap();
+        let (isbn, title) = (book.get_isbn().clone(), book.get_title().to_string());
+        let today = self.today();
+        let result = self.library.as_mut().unwrap().return_book(&isbn, today);
+        if result.is_ok() {
+            self.changed();
+        }
+        self.notify_result(
+            result.map(|_| tr!("Returned \"{}\"", title)),
+            Level::Warning,
+        );
+    }
+
+    pub fn place_hold(&mut self) {
+        let patron = self.patron();
+        let book = self.selected_book.as_ref().unwrap();
+        let (isbn, title) = (book.get_isbn().clone(), book.get_title().to_string());
+        let today = self.today();
+        let result = self
+            .library
+            .as_mut()
             .unwrap()
-            .save(&self.config.library_path)
-            .expect("Couldn't create library"); //todo: handle error
+            .place_hold(&isbn, &patron, today);
+        if result.is_ok() {
+            self.changed();
+        }
+        self.notify_result(
+            result.map(|_| tr!("Placed a hold on \"{}\"", title)),
+            Level::Warning,
+        );
+    }
+
+    /// Shows a toast, and keeps it in the message history.
+    pub fn notify(&mut self, level: Level, text: String) {
+        let time = self.now();
+        self.notifications.push(level, text, time);
+    }
+
+    /// Shows how something went: successes as info, failures at `failure`.
+    pub fn notify_result<E: ToString>(&mut self, result: Result<String, E>, failure: Level) {
+        match result {
+            Ok(text) => self.notify(Level::Info, text),
+            Err(e) => self.notify(failure, e.to_string()),
+        }
+    }
+
+    /// The book the Home cursor is on.
+    pub fn highlighted_book(&self) -> Option<&Book> {
+        match self.tab {
+            Tab::Catalog => {
+                let i = *self.catalog_view().get(self.home_state.selected()?)?;
+                self.library.as_ref()?.get_books().get(i)
+            }
+            Tab::Loans => {
+                let library = self.library.as_ref()?;
+                let loans = library.active_loans();
+                let loan = loans.get(self.loans_state.selected()?)?;
+                library.find(&loan.isbn)
+            }
+            Tab::Patrons | Tab::Reports => None,
+        }
+    }
+
+    pub fn initialize_demo(&mut self) {
+        self.set_library(Some(Library::initialize_demo(self.owner_input.value())));
+        self.request(Effect::SaveLibrary);
         self.loaded();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone};
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    /// An app in English at 09:30 on 14 March 2024, before loading.
+    fn new_app() -> App {
+        let config = Config {
+            language: Some(Language::English),
+            ..Config::default()
+        };
+        let mut app = App::with_config(config);
+        app.clock = Some(Local.with_ymd_and_hms(2024, 3, 14, 9, 30, 0).unwrap());
+        app
+    }
+
+    /// Like `new_app`, showing the catalog of the snapshot tests' library.
+    fn app() -> App {
+        let mut app = new_app();
+        let library = serde_json::from_str(include_str!("testdata/library.json")).unwrap();
+        app.set_library(Some(library));
+        app.loaded = true;
+        app.current_screen = CurrentScreen::Home;
+        app
+    }
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn advance(app: &mut App, by: TimeDelta) {
+        app.clock = Some(app.now() + by);
+    }
+
+    #[test]
+    fn loading_spawns_a_task() {
+        let mut app = new_app();
+        match &app.update(Action::Confirm)[..] {
+            [Effect::Spawn(Task::Load(path)), Effect::Render] => assert_eq!(path, "library.json"),
+            effects => panic!("unexpected effects: {:?}", effects),
+        }
+    }
+
+    #[test]
+    fn cancelling_a_load_goes_back_to_the_prompt() {
+        let mut app = new_app();
+        app.start_task(Task::Load("missing/library.json".to_string()));
+        assert!(matches!(app.update(Action::Back)[..], [Effect::Render]));
+        assert!(app.task.is_none());
+        assert!(matches!(app.current_screen, CurrentScreen::Loading));
+        app.tick();
+        assert!(matches!(app.current_screen, CurrentScreen::Loading));
+        assert!(matches!(
+            app.update(Action::Back)[..],
+            [Effect::Quit(false), Effect::Render]
+        ));
+    }
+
+    #[test]
+    fn quitting_asks_first() {
+        let mut app = app();
+        assert!(matches!(app.update(Action::Quit)[..], [Effect::Render]));
+        assert!(matches!(app.current_screen, CurrentScreen::Exiting));
+        assert!(matches!(
+            app.update(Action::Yes)[..],
+            [Effect::Quit(true), Effect::Render]
+        ));
+    }
+
+    #[test]
+    fn sorting_saves_the_config() {
+        let mut app = app();
+        assert!(matches!(
+            app.handle_key(key('1'))[..],
+            [Effect::SaveConfig, Effect::Render]
+        ));
+        assert!(app.handle_key(key('~')).is_empty());
+    }
+
+    #[test]
+    fn checkouts_are_dated_by_the_clock_and_autosaved() {
+        let mut app = app();
+        // Dune, which is available.
+        app.home_state.select(Some(3));
+        app.update(Action::Confirm);
+        assert!(matches!(app.update(Action::Confirm)[..], [Effect::Render]));
+        let isbn = app.selected_book.as_ref().unwrap().get_isbn().clone();
+        let loan = app.library.as_ref().unwrap().current_loan(&isbn).unwrap();
+        assert_eq!(
+            (loan.patron.as_str(), loan.checked_out),
+            ("Ana", app.today())
+        );
+        assert_eq!(app.unsaved_since, app.clock);
+
+        let saves = |effects: Vec<Effect>| effects.iter().any(|e| matches!(e, Effect::SaveLibrary));
+        advance(&mut app, TimeDelta::minutes(4));
+        assert!(!saves(app.tick()));
+        advance(&mut app, TimeDelta::minutes(1));
+        assert!(saves(app.tick()));
+    }
+
+    #[test]
+    fn toasts_expire_on_the_clock() {
+        let mut app = app();
+        app.notify(Level::Info, "Saved".to_string());
+        advance(&mut app, TimeDelta::seconds(2));
+        app.tick();
+        assert_eq!(app.notifications.toasts().count(), 1);
+        advance(&mut app, TimeDelta::seconds(1));
+        app.tick();
+        assert_eq!(app.notifications.toasts().count(), 0);
+    }
+
+    #[test]
+    fn large_catalogs_search_after_a_pause() {
+        let mut app = app();
+        let mut library = Library::new("Ana");
+        for i in 0..=SEARCH_DEBOUNCE_BOOKS {
+            let isbn = Isbn::from(i.to_string());
+            library.add(Book::new("Book", "Someone", isbn, 2000, Genre::Other));
+        }
+        app.set_library(Some(library));
+        app.update(Action::Search);
+        app.handle_key(key('7'));
+        assert_eq!(app.search_query, "");
+        assert_eq!(app.search_timeout(), Some(SEARCH_DEBOUNCE));
+        advance(&mut app, TimeDelta::milliseconds(150));
+        app.tick();
+        assert_eq!(app.search_query, "7");
+    }
+}
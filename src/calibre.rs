@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::isbn::Isbn;
+use crate::library::{Book, Genre, Library, Series};
+
+/// Outcome of a Calibre import: how many books were added, and which
+/// records were merged into existing books or skipped (with the reason).
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub merged: Vec<String>,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// The books read from a Calibre library, before they are added to one.
+#[derive(Debug, Default)]
+pub struct CalibreBooks {
+    books: Vec<Book>,
+    skipped: Vec<(String, String)>,
+}
+
+struct CalibreBook {
+    id: i64,
+    title: String,
+    pubdate: Option<String>,
+    legacy_isbn: Option<String>,
+}
+
+/// Accepts either the Calibre library folder or the `metadata.db` file itself.
+fn metadata_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join("metadata.db")
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Calibre stores unknown publication dates as year 101.
+fn publication_year(pubdate: Option<&str>) -> u16 {
+    match pubdate
+        .and_then(|d| d.get(0..4))
+        .and_then(|y| y.parse().ok())
+    {
+        Some(year) if year > 101 => year,
+        _ => 0,
+    }
+}
+
+/// Collects `(book id, value)` pairs from a query into a map of lists.
+fn grouped(conn: &Connection, sql: &str) -> rusqlite::Result<HashMap<i64, Vec<String>>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get(1)?)))?;
+    let mut map: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (book, value) = row?;
+        map.entry(book).or_default().push(value);
+    }
+    Ok(map)
+}
+
+fn series(conn: &Connection) -> rusqlite::Result<HashMap<i64, String>> {
+    let mut stmt = conn.prepare(
+        "SELECT books_series_link.book, series.name FROM books_series_link \
+         JOIN series ON series.id = books_series_link.series",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+fn books(conn: &Connection) -> rusqlite::Result<Vec<(CalibreBook, f64)>> {
+    let mut stmt =
+        conn.prepare("SELECT id, title, pubdate, isbn, series_index FROM books ORDER BY id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            CalibreBook {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                pubdate: row.get(2)?,
+                legacy_isbn: row.get(3)?,
+            },
+            row.get::<_, Option<f64>>(4)?.unwrap_or(1.0),
+        ))
+    })?;
+    rows.collect()
+}
+
+/// Reads a Calibre library's `metadata.db` and adds its books to `library`.
+///
+/// Books are matched by ISBN: records without one are skipped, and records
+/// whose ISBN is already in the library only fill in missing fields.
+pub fn import(library: &mut Library, path: &Path) -> rusqlite::Result<ImportReport> {
+    Ok(merge(library, read(path, &mut |_, _| {})?))
+}
+
+/// Reads the books of a Calibre library's `metadata.db`, calling `progress`
+/// with the records read so far and how many there are.
+pub fn read(path: &Path, progress: &mut dyn FnMut(usize, usize)) -> rusqlite::Result<CalibreBooks> {
+    let conn = Connection::open_with_flags(metadata_path(path), OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let authors = grouped(
+        &conn,
+        "SELECT books_authors_link.book, authors.name FROM books_authors_link \
+         JOIN authors ON authors.id = books_authors_link.author \
+         ORDER BY books_authors_link.id",
+    )?;
+    let tags = grouped(
+        &conn,
+        "SELECT books_tags_link.book, tags.name FROM books_tags_link \
+         JOIN tags ON tags.id = books_tags_link.tag \
+         ORDER BY books_tags_link.id",
+    )?;
+    let isbns = grouped(
+        &conn,
+        "SELECT book, val FROM identifiers WHERE lower(type) = 'isbn'",
+    )?;
+    let series = series(&conn)?;
+
+    let mut read = CalibreBooks::default();
+    let records = books(&conn)?;
+    let total = records.len();
+    for (i, (record, series_index)) in records.into_iter().enumerate() {
+        progress(i + 1, total);
+        let isbn = isbns
+            .get(&record.id)
+            .and_then(|values| values.first().cloned())
+            .or(record.legacy_isbn)
+            .filter(|value| !value.trim().is_empty());
+        let isbn = match isbn.as_deref().map(Isbn::parse) {
+            Some(Ok(isbn)) => isbn,
+            Some(Err(e)) => {
+                read.skipped.push((record.title, e.to_string()));
+                continue;
+            }
+            None => {
+                read.skipped
+                    .push((record.title, "no ISBN identifier".to_string()));
+                continue;
+            }
+        };
+
+        let author = match authors.get(&record.id) {
+            Some(names) => names.join(" & "),
+            None => "Unknown".to_string(),
+        };
+        let book_tags = tags.get(&record.id).cloned().unwrap_or_default();
+        let genre = book_tags
+            .iter()
+            .find_map(|tag| Genre::from_tag(tag))
+            .unwrap_or(Genre::Other);
+        let book = Book::new(
+            &record.title,
+            &author,
+            isbn,
+            publication_year(record.pubdate.as_deref()),
+            genre,
+        )
+        .with_series(series.get(&record.id).map(|name| Series {
+            name: name.clone(),
+            index: series_index,
+        }))
+        .with_tags(book_tags);
+        read.books.push(book);
+    }
+    Ok(read)
+}
+
+/// Adds books read from Calibre to `library`.
+pub fn merge(library: &mut Library, read: CalibreBooks) -> ImportReport {
+    let mut report = ImportReport {
+        skipped: read.skipped,
+        ..ImportReport::default()
+    };
+    for book in read.books {
+        match library.find_mut(book.get_isbn()) {
+            Some(existing) => {
+                if existing.merge_from(&book) {
+                    report.merged.push(book.get_title().to_string());
+                }
+            }
+            None => {
+                library.add(book);
+                report.imported += 1;
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gatsby() -> Book {
+        let isbn = Isbn::parse("9780743273565").unwrap();
+        Book::new(
+            "The Great Gatsby",
+            "F. Scott Fitzgerald",
+            isbn,
+            1925,
+            Genre::Fiction,
+        )
+    }
+
+    /// A `metadata.db` with the tables `read` uses: Dune with an ISBN
+    /// identifier, Gatsby with only the legacy ISBN column, and two books
+    /// without a usable ISBN.
+    fn metadata_db(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("libros-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT, pubdate TEXT, \
+                 isbn TEXT, series_index REAL);
+             CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER);
+             CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER, tag INTEGER);
+             CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT, val TEXT);
+             CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER, series INTEGER);
+             INSERT INTO books VALUES
+                 (1, 'Dune', '1965-08-01 00:00:00+00:00', '', 1.0),
+                 (2, 'The Great Gatsby', '0101-01-01 00:00:00+00:00', '0-7432-7356-7', 1.0),
+                 (3, 'Untitled', '2001-01-01 00:00:00+00:00', '', 1.0),
+                 (4, 'Misprint', '2001-01-01 00:00:00+00:00', '', 1.0);
+             INSERT INTO authors VALUES (1, 'Frank Herbert'), (2, 'Kevin J. Anderson'),
+                 (3, 'F. Scott Fitzgerald');
+             INSERT INTO books_authors_link (book, author) VALUES (1, 1), (1, 2), (2, 3);
+             INSERT INTO tags VALUES (1, 'Classics'), (2, 'Sci-Fi');
+             INSERT INTO books_tags_link (book, tag) VALUES (1, 1), (1, 2), (2, 1);
+             INSERT INTO identifiers (book, type, val) VALUES
+                 (1, 'ISBN', '978-0-441-17271-9'), (3, 'amazon', 'B000'), (4, 'isbn', '12345');
+             INSERT INTO series VALUES (1, 'Dune');
+             INSERT INTO books_series_link (book, series) VALUES (1, 1);",
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_and_merges_a_calibre_library() {
+        let path = metadata_db("metadata.db");
+        let mut progress = Vec::new();
+        let read = read(&path, &mut |done, total| progress.push((done, total))).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(progress, [(1, 4), (2, 4), (3, 4), (4, 4)]);
+
+        let mut library = Library::new("Ana");
+        library.add(gatsby());
+        let report = merge(&mut library, read);
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.merged, ["The Great Gatsby"]);
+        assert_eq!(report.skipped[0].0, "Untitled");
+        assert_eq!(report.skipped[0].1, "no ISBN identifier");
+        assert_eq!(report.skipped[1].0, "Misprint");
+        assert_eq!(report.skipped.len(), 2);
+
+        let books = library.get_books();
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].get_tags(), ["Classics"]);
+        let dune = &books[1];
+        assert_eq!(dune.get_isbn().as_str(), "9780441172719");
+        assert_eq!(dune.get_author(), "Frank Herbert & Kevin J. Anderson");
+        assert_eq!(dune.get_publication_year(), 1965);
+        assert_eq!(*dune.get_genre(), Genre::ScienceFiction);
+        assert_eq!(dune.get_series().unwrap().to_string(), "Dune #1");
+        assert_eq!(dune.get_tags(), ["Classics", "Sci-Fi"]);
+    }
+
+    #[test]
+    fn merge_counts_only_books_that_changed() {
+        let mut library = Library::initialize_demo("Ana");
+        let read = CalibreBooks {
+            books: vec![gatsby(), gatsby().with_tags(vec!["classic".to_string()])],
+            skipped: Vec::new(),
+        };
+        let report = merge(&mut library, read);
+        assert_eq!(report.imported, 0);
+        assert_eq!(report.merged, ["The Great Gatsby"]);
+    }
+
+    #[test]
+    fn merge_adds_new_books() {
+        let mut library = Library::new("Ana");
+        let read = CalibreBooks {
+            books: vec![gatsby()],
+            skipped: vec![("Untitled".to_string(), "no ISBN".to_string())],
+        };
+        let report = merge(&mut library, read);
+        assert_eq!(report.imported, 1);
+        assert!(report.merged.is_empty());
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(library.get_books().len(), 1);
+    }
+}
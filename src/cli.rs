@@ -0,0 +1,151 @@
+use std::error::Error;
+use std::path::Path;
+
+use crate::app::recovery_path;
+use crate::config::{Config, CONFIG_PATH};
+use crate::i18n::{self, tr, Language};
+use crate::library::Library;
+use crate::{calibre, html, metadata, opds};
+
+const USAGE: &str = "Usage:
+  libros                              start the interactive library
+  libros import-calibre <library>     import books from a Calibre library or metadata.db
+  libros export-html <dir>            write the catalog as a static HTML site
+  libros opds <dir>                   write the catalog as OPDS feeds
+  libros opds --serve [addr]          serve the OPDS feeds (default 127.0.0.1:8080)
+  libros export <jsonld|dc>           print the catalog as schema.org JSON-LD or Dublin Core XML";
+
+/// Unsaved changes the app left behind, which its next start restores in
+/// place of the library file.
+fn pending_recovery(config: &Config) -> Option<String> {
+    let path = recovery_path(&config.library_path);
+    Path::new(&path).exists().then_some(path)
+}
+
+fn load_library(config: &Config) -> Result<Library, Box<dyn Error>> {
+    if let Some(path) = pending_recovery(config) {
+        eprintln!(
+            "{}",
+            tr!(
+                "Unsaved changes in {} are left out; start libros to restore them",
+                path
+            )
+        );
+    }
+    Library::from_file(&config.library_path)
+        .map_err(|e| tr!("Couldn't load {}: {}", config.library_path, e))?
+        .ok_or_else(|| {
+            tr!(
+                "No library found at {}, run libros once to create it",
+                config.library_path
+            )
+            .into()
+        })
+}
+
+fn import_calibre(config: &Config, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [path] = args else {
+        return Err(USAGE.into());
+    };
+    // The app would restore the recovery file over the imported books.
+    if let Some(recovery) = pending_recovery(config) {
+        return Err(tr!(
+            "Unsaved changes are waiting in {}; start libros to restore them before importing",
+            recovery
+        )
+        .into());
+    }
+    let mut library = load_library(config)?;
+    let report = calibre::import(&mut library, Path::new(path))?;
+    library.save(&config.library_path)?;
+
+    println!("{}", tr!("Imported {} book(s)", report.imported));
+    println!(
+        "{}",
+        tr!(
+            "Merged {} record(s) into existing books",
+            report.merged.len()
+        )
+    );
+    for title in report.merged.iter() {
+        println!("  {}", tr!("merged: {}", title));
+    }
+    println!("{}", tr!("Skipped {} record(s)", report.skipped.len()));
+    for (title, reason) in report.skipped.iter() {
+        println!("  {}", tr!("skipped: {} ({})", title, reason));
+    }
+    Ok(())
+}
+
+fn export_html(config: &Config, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [dir] = args else {
+        return Err(USAGE.into());
+    };
+    let library = load_library(config)?;
+    html::export(&library, Path::new(dir))?;
+    println!(
+        "{}",
+        tr!(
+            "Wrote {} book page(s) to {}",
+            library.get_books().len(),
+            dir
+        )
+    );
+    Ok(())
+}
+
+fn export(config: &Config, args: &[String]) -> Result<(), Box<dyn Error>> {
+    let [format] = args else {
+        return Err(USAGE.into());
+    };
+    let library = load_library(config)?;
+    match format.as_str() {
+        "jsonld" => println!(
+            "{}",
+            serde_json::to_string_pretty(&metadata::catalog_json_ld(&library))?
+        ),
+        "dc" => print!("{}", metadata::catalog_dublin_core(&library)),
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+fn opds(config: &Config, args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args {
+        [flag] if flag == "--serve" => {
+            load_library(config)?;
+            opds::serve(&config.library_path, "127.0.0.1:8080")?;
+        }
+        [flag, addr] if flag == "--serve" => {
+            load_library(config)?;
+            opds::serve(&config.library_path, addr)?;
+        }
+        [dir] => {
+            let library = load_library(config)?;
+            let written = opds::write(&library, Path::new(dir))?;
+            println!("{}", tr!("Wrote {} feed(s) to {}", written, dir));
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+/// Runs a non-interactive subcommand, e.g. `libros import-calibre ~/Calibre`.
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let (config, warnings) =
+        Config::load(CONFIG_PATH).map_err(|e| tr!("Couldn't read {}: {}", CONFIG_PATH, e))?;
+    i18n::set_language(config.language.unwrap_or_else(Language::from_env));
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    match args.split_first() {
+        Some((command, rest)) => match command.as_str() {
+            "import-calibre" => import_calibre(&config, rest),
+            "export" => export(&config, rest),
+            "export-html" => export_html(&config, rest),
+            "opds" => opds(&config, rest),
+            _ => Err(USAGE.into()),
+        },
+        None => Err(USAGE.into()),
+    }
+}
@@ -0,0 +1,411 @@
+use std::cmp::Reverse;
+use std::path::PathBuf;
+
+use crate::app::{App, CurrentScreen, Effect};
+use crate::calibre::{self, CalibreBooks};
+use crate::i18n::{self, t, tr, Language};
+use crate::input::TextInput;
+use crate::keymap::{Action, KeyContext, Keymap, Preset};
+use crate::library::Library;
+use crate::notifications::Level;
+use crate::tasks::Task;
+use crate::theme::{Theme, ThemeName};
+
+/// Something the command palette can run: any action of the screen below
+/// it, plus commands that have no key of their own.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Command {
+    Run(Action),
+    SetTheme(ThemeName),
+    SetKeymap(Preset),
+    SetLanguage(Language),
+    ExportHtml,
+    ExportOpds,
+    ImportCalibre,
+    SwitchLibrary,
+    SetPatron,
+}
+
+impl Command {
+    /// What to ask for before running, and the answer to start from.
+    fn argument(self, app: &App) -> Option<(&'static str, String)> {
+        match self {
+            Command::ExportHtml => Some((t("Directory for the HTML site"), "site".to_string())),
+            Command::ExportOpds => Some((t("Directory for the OPDS feeds"), "opds".to_string())),
+            Command::ImportCalibre => Some((t("Calibre library or metadata.db"), String::new())),
+            Command::SwitchLibrary => Some((t("Library file"), app.config.library_path.clone())),
+            Command::SetPatron => Some((t("Patron (empty for the owner)"), app.patron())),
+            _ => None,
+        }
+    }
+}
+
+/// What `action` does in `context`, and the group it's listed under in help.
+pub fn describe(context: KeyContext, action: Action) -> (&'static str, &'static str) {
+    use Action::*;
+    match action {
+        MoveUp if matches!(context, KeyContext::Help | KeyContext::Messages) => {
+            ("Navigation", "Scroll up")
+        }
+        MoveUp if context == KeyContext::Palette => ("Navigation", "Previous command"),
+        MoveDown if context == KeyContext::Palette => ("Navigation", "Next command"),
+        MoveDown if matches!(context, KeyContext::Help | KeyContext::Messages) => {
+            ("Navigation", "Scroll down")
+        }
+        MoveUp => ("Navigation", "Move up"),
+        MoveDown => ("Navigation", "Move down"),
+        PageUp => ("Navigation", "Page up"),
+        PageDown => ("Navigation", "Page down"),
+        First => ("Navigation", "Go to the top"),
+        Last => ("Navigation", "Go to the bottom"),
+        NextField => ("Navigation", "Next filter"),
+        PrevField => ("Navigation", "Previous filter"),
+        NextValue => ("Actions", "Next value for the filter"),
+        PrevValue => ("Actions", "Previous value for the filter"),
+        Confirm => (
+            "Actions",
+            match context {
+                KeyContext::Palette => "Run the command",
+                KeyContext::Loading => "Load the library",
+                KeyContext::NewOwner => "Create the library",
+                KeyContext::Book => "Check out the book",
+                KeyContext::Filtering => "Close the filter bar",
+                KeyContext::Patrons => "Check out and place holds for this patron",
+                _ => "Open the selected book",
+            },
+        ),
+        Search => ("Actions", "Search the catalog"),
+        Filter if context == KeyContext::Filtering => ("Actions", "Close the filter bar"),
+        Filter => ("Actions", "Filter the catalog"),
+        Columns if context == KeyContext::Columns => ("Actions", "Stop choosing columns"),
+        Columns => ("Actions", "Choose the visible columns"),
+        ToggleSearchInput if context == KeyContext::SearchInput => {
+            ("Actions", "Pick what to search by")
+        }
+        ToggleSearchInput => ("Actions", "Type the query"),
+        SearchByTitle => ("Actions", "Search by title"),
+        SearchByAuthor => ("Actions", "Search by author"),
+        SearchByIsbn => ("Actions", "Search by ISBN"),
+        ClearFilter => ("Actions", "Clear all filters"),
+        ReturnBook => ("Actions", "Return the book"),
+        PlaceHold => ("Actions", "Place a hold"),
+        Yes => ("Actions", "Exit and save"),
+        No => ("Actions", "Stay"),
+        Back if context == KeyContext::Loading => {
+            ("General", "Stop loading, or exit without loading")
+        }
+        Back if context == KeyContext::Help => ("General", "Close help"),
+        Back if context == KeyContext::Palette => ("General", "Close the palette"),
+        Back if context == KeyContext::Messages => ("General", "Close the messages"),
+        Back if matches!(
+            context,
+            KeyContext::Loans | KeyContext::Patrons | KeyContext::Reports
+        ) =>
+        {
+            ("General", "Go back to the catalog")
+        }
+        Back => ("General", "Go back to the main screen"),
+        BackToSearch => ("General", "Go back to the search"),
+        Quit => ("General", "Quit"),
+        Help => ("General", "Show or hide the key bindings"),
+        Palette => ("General", "Open the command palette"),
+        Dashboard if context == KeyContext::Reports => ("General", "Go back to the catalog"),
+        Dashboard => ("Actions", "Show the collection statistics"),
+        NextTab => ("Navigation", "Next tab"),
+        PrevTab => ("Navigation", "Previous tab"),
+        Messages if context == KeyContext::Messages => ("General", "Close the messages"),
+        Messages => ("General", "Show recent messages"),
+    }
+}
+
+/// The open command palette.
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub query: TextInput,
+    /// Index into the matching commands.
+    pub selected: usize,
+    /// A chosen command that is waiting for its argument.
+    pub pending: Option<(Command, &'static str, TextInput)>,
+}
+
+impl Palette {
+    /// The field being typed into.
+    pub fn input(&mut self) -> &mut TextInput {
+        match &mut self.pending {
+            Some((_, _, argument)) => argument,
+            None => &mut self.query,
+        }
+    }
+}
+
+/// How well `query` matches `text`: every query character must appear in
+/// order, and runs of adjacent characters and word starts score higher.
+/// `None` when it doesn't match at all.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + text[position..].iter().position(|c| *c == q)?;
+        score += 1;
+        if previous == Some(found.wrapping_sub(1)) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        score -= (found - position) as i32 / 4;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+impl App {
+    /// Every command the palette offers right now, with its title.
+    pub fn commands(&self) -> Vec<(Command, String)> {
+        let context = self.screen_context();
+        let mut commands: Vec<(Command, String)> = context
+            .actions()
+            .iter()
+            .filter(|action| **action != Action::Palette)
+            .map(|action| {
+                (
+                    Command::Run(*action),
+                    t(describe(context, *action).1).to_string(),
+                )
+            })
+            .collect();
+        if self.library.is_some() {
+            commands.extend([
+                (
+                    Command::ExportHtml,
+                    t("Export the catalog as HTML").to_string(),
+                ),
+                (
+                    Command::ExportOpds,
+                    t("Export the catalog as OPDS feeds").to_string(),
+                ),
+                (Command::ImportCalibre, t("Import from Calibre").to_string()),
+                (Command::SwitchLibrary, t("Switch library").to_string()),
+                (Command::SetPatron, t("Change the patron").to_string()),
+            ]);
+        }
+        for theme in ThemeName::ALL {
+            commands.push((Command::SetTheme(theme), tr!("Theme: {}", theme)));
+        }
+        for preset in Preset::ALL {
+            commands.push((Command::SetKeymap(preset), tr!("Key bindings: {}", preset)));
+        }
+        for language in Language::ALL {
+            commands.push((
+                Command::SetLanguage(language),
+                tr!("Language: {}", language.name()),
+            ));
+        }
+        commands
+    }
+
+    /// The commands matching the palette query, best match first.
+    pub fn palette_matches(&self) -> Vec<(Command, String)> {
+        let Some(palette) = &self.palette else {
+            return Vec::new();
+        };
+        let mut matches: Vec<(i32, (Command, String))> = self
+            .commands()
+            .into_iter()
+            .filter_map(|(command, title)| {
+                fuzzy_score(palette.query.value(), &title).map(|score| (score, (command, title)))
+            })
+            .collect();
+        matches.sort_by_key(|(score, _)| Reverse(*score));
+        matches.into_iter().map(|(_, command)| command).collect()
+    }
+
+    /// Handles the palette's keys: moving through the matches, running one,
+    /// or backing out of an argument and then the palette.
+    pub fn perform_in_palette(&mut self, action: Action) {
+        let count = self.palette_matches().len();
+        let Some(palette) = self.palette.as_mut() else {
+            return;
+        };
+        match action {
+            Action::MoveUp => palette.selected = palette.selected.saturating_sub(1),
+            Action::MoveDown => {
+                palette.selected = (palette.selected + 1).min(count.saturating_sub(1))
+            }
+            Action::Back if palette.pending.is_some() => palette.pending = None,
+            Action::Back => self.palette = None,
+            Action::Confirm => match palette.pending.take() {
+                Some((command, _, argument)) => {
+                    self.palette = None;
+                    self.run_command(command, argument.value());
+                }
+                None => {
+                    let selected = palette.selected;
+                    if let Some((command, _)) = self.palette_matches().get(selected).cloned() {
+                        self.choose_command(command);
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Runs `command`, or asks for its argument first.
+    fn choose_command(&mut self, command: Command) {
+        match command.argument(self) {
+            Some((label, value)) => {
+                let palette = self.palette.as_mut().unwrap();
+                palette.pending = Some((command, label, TextInput::with_value(&value)));
+            }
+            None => {
+                self.palette = None;
+                self.run_command(command, "");
+            }
+        }
+    }
+
+    fn run_command(&mut self, command: Command, argument: &str) {
+        let argument = argument.trim();
+        let text = match command {
+            Command::Run(action) => {
+                self.perform(action);
+                return;
+            }
+            Command::SetTheme(theme) => {
+                self.config.theme = theme;
+                self.theme = Theme::load(theme);
+                self.request(Effect::SaveConfig);
+                tr!("Theme set to {}", theme)
+            }
+            Command::SetKeymap(preset) => {
+                self.config.keymap = preset;
+                self.keymap = Keymap::new(preset, &self.config.key_bindings);
+                self.request(Effect::SaveConfig);
+                tr!("Key bindings set to {}", preset)
+            }
+            Command::SetLanguage(language) => {
+                self.config.language = Some(language);
+                i18n::set_language(language);
+                self.request(Effect::SaveConfig);
+                tr!("Language set to {}", language.name())
+            }
+            Command::ExportHtml => {
+                let library = self.library.clone().unwrap();
+                let task = Task::ExportHtml(library, PathBuf::from(argument));
+                self.request(Effect::Spawn(task));
+                return;
+            }
+            Command::ExportOpds => {
+                let library = self.library.clone().unwrap();
+                let task = Task::ExportOpds(library, PathBuf::from(argument));
+                self.request(Effect::Spawn(task));
+                return;
+            }
+            Command::ImportCalibre => {
+                let task = Task::ImportCalibre(PathBuf::from(argument));
+                self.request(Effect::Spawn(task));
+                return;
+            }
+            Command::SetPatron => {
+                self.set_patron(argument);
+                return;
+            }
+            Command::SwitchLibrary => {
+                if self.library.is_some() {
+                    self.request(Effect::SaveLibrary);
+                }
+                self.request(Effect::Spawn(Task::Open(argument.to_string())));
+                return;
+            }
+        };
+        self.notify(Level::Info, text);
+    }
+
+    /// Adds the books an import read to the library and asks for it to be
+    /// saved.
+    pub fn merge_import(&mut self, read: CalibreBooks) -> String {
+        let library = self.library.as_mut().unwrap();
+        let report = calibre::merge(library, read);
+        self.changed();
+        self.request(Effect::SaveLibrary);
+        self.move_cursor(0);
+        tr!(
+            "Imported {} book(s), merged {}, skipped {}",
+            report.imported,
+            report.merged.len(),
+            report.skipped.len()
+        )
+    }
+
+    /// Swaps in the library a `Task::Open` read from `path`, or starts a new
+    /// one there if `library` is `None`.
+    pub fn switch_library(
+        &mut self,
+        path: &str,
+        library: Option<Library>,
+    ) -> Result<String, String> {
+        // Saving the current library failed; keep it open.
+        if self.unsaved_since.is_some() {
+            return Err(tr!(
+                "Couldn't open {}: {}",
+                path,
+                t("the current library has unsaved changes")
+            ));
+        }
+        self.config.library_path = path.to_string();
+        self.request(Effect::SaveConfig);
+        self.selected_book = None;
+        self.home_state.select(Some(0));
+        match library {
+            Some(library) => {
+                self.set_library(Some(library));
+                self.current_screen = CurrentScreen::Home;
+                self.move_cursor(0);
+                Ok(tr!("Opened {}", path))
+            }
+            None => {
+                self.set_library(None);
+                self.loaded = false;
+                self.owner_input.clear();
+                self.current_screen = CurrentScreen::NewOwner;
+                Ok(tr!("Creating a new library at {}", path))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_rank_above_subsequences() {
+        let prefix = fuzzy_score("ret", "Return the book").unwrap();
+        let scattered = fuzzy_score("ret", "Previous tab").unwrap();
+        assert!(prefix > scattered);
+        let word_start = fuzzy_score("bo", "Return the book").unwrap();
+        let inside = fuzzy_score("bo", "Elbow room").unwrap();
+        assert!(word_start > inside);
+    }
+
+    #[test]
+    fn rejects_non_matches() {
+        assert_eq!(fuzzy_score("xyz", "Return the book"), None);
+        assert_eq!(fuzzy_score("returns", "Return"), None);
+        assert_eq!(fuzzy_score("kb", "Back"), None);
+        assert_eq!(fuzzy_score("", "Return"), Some(0));
+    }
+
+    #[test]
+    fn ignores_case_and_spaces() {
+        assert_eq!(fuzzy_score("RET", "return"), fuzzy_score("ret", "RETURN"));
+        assert_eq!(
+            fuzzy_score("re turn", "Return"),
+            fuzzy_score("return", "Return")
+        );
+        assert!(fuzzy_score("ÉX", "éxito").is_some());
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app::{Column, SortOrder};
+use crate::i18n::{t, tr, Language};
+use crate::keymap::{Action, Key, Preset};
+use crate::theme::ThemeName;
+
+pub const CONFIG_PATH: &str = "config.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub library_path: String,
+    /// Catalog table columns to show, in display order.
+    pub columns: Vec<Column>,
+    /// Catalog sort; `None` keeps the order books were added in.
+    pub sort: Option<(Column, SortOrder)>,
+    /// Ignored when `NO_COLOR` is set, which always means monochrome.
+    pub theme: ThemeName,
+    pub keymap: Preset,
+    /// `None` follows `LANG`.
+    pub language: Option<Language>,
+    /// Keys for individual actions, replacing the preset's.
+    pub key_bindings: BTreeMap<Action, Vec<Key>>,
+    /// How long changes wait before the library is saved; 0 only saves on
+    /// exit.
+    pub autosave_minutes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::new("library.json")
+    }
+}
+
+impl Config {
+    pub fn new(library_path: &str) -> Config {
+        Config {
+            library_path: String::from(library_path),
+            columns: Column::ALL.to_vec(),
+            sort: None,
+            theme: ThemeName::Default,
+            keymap: Preset::Default,
+            language: None,
+            key_bindings: BTreeMap::new(),
+            autosave_minutes: 5,
+        }
+    }
+
+    /// Reads the config at `pathname`, falling back to defaults for a
+    /// missing file or missing fields. An unknown theme falls back to the
+    /// default one and key bindings that don't parse are left out one by one,
+    /// each described in the returned warnings.
+    pub fn load(pathname: &str) -> Result<(Config, Vec<String>), io::Error> {
+        let s = match read_to_string(Path::new(pathname)) {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok((Config::default(), Vec::new()))
+            }
+            Err(e) => return Err(e),
+        };
+        let mut value: Value = serde_json::from_str(&s)?;
+        let bindings = value.as_object_mut().and_then(|o| o.remove("key_bindings"));
+        let theme = value.as_object_mut().and_then(|o| o.remove("theme"));
+        let mut config: Config = serde_json::from_value(value)?;
+        let mut warnings = Vec::new();
+        if let Some(theme) = theme {
+            match serde_json::from_value(theme.clone()) {
+                Ok(theme) => config.theme = theme,
+                Err(_) => warnings.push(tr!("Unknown theme {}, using the default", theme)),
+            }
+        }
+        match bindings {
+            Some(Value::Object(bindings)) => {
+                for (name, keys) in bindings {
+                    match parse_binding(&name, keys, &mut warnings) {
+                        Some((action, keys)) => {
+                            config.key_bindings.insert(action, keys);
+                        }
+                        None => warnings.push(tr!("Ignoring the key binding for '{}'", name)),
+                    }
+                }
+            }
+            Some(Value::Null) | None => {}
+            Some(_) => {
+                warnings.push(t("Ignoring key_bindings, which should be an object").to_string())
+            }
+        }
+        Ok((config, warnings))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), io::Error> {
+        let json = serde_json::to_string_pretty(&self).unwrap();
+        std::fs::write(path, json)
+    }
+}
+
+/// One `key_bindings` entry. Keys that don't parse are left out with a
+/// warning; `None` when the action is unknown or none of its keys parse.
+fn parse_binding(
+    name: &str,
+    keys: Value,
+    warnings: &mut Vec<String>,
+) -> Option<(Action, Vec<Key>)> {
+    let action: Action = serde_json::from_value(Value::String(name.to_string())).ok()?;
+    let Value::Array(keys) = keys else {
+        return None;
+    };
+    let written = keys.len();
+    let mut parsed = Vec::new();
+    for key in keys {
+        match key.as_str().map(str::parse::<Key>) {
+            Some(Ok(key)) => parsed.push(key),
+            Some(Err(e)) => warnings.push(e),
+            None => warnings.push(tr!("Key {} for '{}' should be a string", key, name)),
+        }
+    }
+    // An empty list unbinds the action on purpose; a list of typos doesn't.
+    match parsed.is_empty() && written > 0 {
+        true => None,
+        false => Some((action, parsed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn load(name: &str, json: &str) -> Result<(Config, Vec<String>), io::Error> {
+        let path = std::env::temp_dir().join(format!("libros-{}-{}", std::process::id(), name));
+        fs::write(&path, json).unwrap();
+        Config::load(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn malformed_file_is_an_error() {
+        assert!(load("malformed-config.json", "{\"theme\": ").is_err());
+        assert!(load("wrong-type-config.json", "{\"autosave_minutes\": \"5\"}").is_err());
+    }
+
+    #[test]
+    fn unknown_themes_fall_back_to_the_default() {
+        let json = r#"{"theme": "Neon", "autosave_minutes": 1}"#;
+        let (config, warnings) = load("theme-config.json", json).unwrap();
+        assert_eq!(config.theme, ThemeName::Default);
+        assert_eq!(config.autosave_minutes, 1);
+        assert_eq!(warnings.len(), 1);
+        let json = r#"{"theme": "HighContrast"}"#;
+        let (config, warnings) = load("theme-config.json", json).unwrap();
+        assert_eq!(config.theme, ThemeName::HighContrast);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn skips_invalid_key_bindings() {
+        let json = r#"{"autosave_minutes": 1, "key_bindings": {
+            "Quit": ["ctrl+q", "hyper+q"],
+            "Search": ["nope"],
+            "Launch": ["l"],
+            "Help": []
+        }}"#;
+        let (config, warnings) = load("bindings-config.json", json).unwrap();
+        assert_eq!(config.autosave_minutes, 1);
+        let bindings: Vec<(Action, Vec<String>)> = config
+            .key_bindings
+            .into_iter()
+            .map(|(action, keys)| (action, keys.iter().map(Key::to_string).collect()))
+            .collect();
+        assert_eq!(
+            bindings,
+            [
+                (Action::Quit, vec!["ctrl+q".to_string()]),
+                (Action::Help, vec![]),
+            ]
+        );
+        assert_eq!(warnings.len(), 4);
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::isbn::Isbn;
+use crate::library::{Book, Library};
+
+const STYLE: &str =
+    "body{font-family:sans-serif;max-width:60em;margin:2em auto;padding:0 1em;color:#222}\
+nav a{margin-right:1em}\
+table{border-collapse:collapse;width:100%}\
+th,td{text-align:left;padding:.3em .6em;border-bottom:1px solid #ddd}\
+.badge{display:inline-block;padding:.1em .5em;border-radius:.8em;font-size:.85em;color:#fff}\
+.available{background:#2e7d32}\
+.unavailable{background:#c62828}";
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// URL-safe identifier, used for anchors and generated file names.
+pub(crate) fn slug(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.to_lowercase().chars() {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// Slugs that are unique within one export: a repeated slug gets a numeric
+/// suffix, and text without letters or digits gets `fallback` instead.
+#[derive(Default)]
+pub(crate) struct Slugs {
+    used: HashSet<String>,
+}
+
+impl Slugs {
+    pub(crate) fn unique(&mut self, text: &str, fallback: &str) -> String {
+        let base = match slug(text) {
+            slug if slug.is_empty() => fallback.to_string(),
+            slug => slug,
+        };
+        let mut candidate = base.clone();
+        let mut n = 2;
+        while !self.used.insert(candidate.clone()) {
+            candidate = format!("{}-{}", base, n);
+            n += 1;
+        }
+        candidate
+    }
+}
+
+/// Where each book, author and genre goes in the site.
+struct Links {
+    files: HashMap<Isbn, String>,
+    authors: HashMap<String, String>,
+    genres: HashMap<String, String>,
+}
+
+impl Links {
+    fn new(books: &[&Book]) -> Links {
+        let (mut files, mut authors, mut genres) =
+            (Slugs::default(), Slugs::default(), Slugs::default());
+        let mut links = Links {
+            files: HashMap::new(),
+            authors: HashMap::new(),
+            genres: HashMap::new(),
+        };
+        for book in books.iter() {
+            links
+                .files
+                .entry(book.get_isbn().clone())
+                .or_insert_with_key(|isbn| {
+                    format!("books/{}.html", files.unique(isbn.as_str(), "book"))
+                });
+            links
+                .authors
+                .entry(book.get_author().to_string())
+                .or_insert_with_key(|author| authors.unique(author, "author"));
+            links
+                .genres
+                .entry(book.get_genre().to_string())
+                .or_insert_with_key(|genre| genres.unique(genre, "genre"));
+        }
+        links
+    }
+
+    fn file(&self, book: &Book) -> &str {
+        &self.files[book.get_isbn()]
+    }
+
+    fn author(&self, book: &Book) -> &str {
+        &self.authors[book.get_author()]
+    }
+
+    fn genre(&self, book: &Book) -> &str {
+        &self.genres[&book.get_genre().to_string()]
+    }
+}
+
+/// Availability badge; uses the same rule as `Book::get_available`.
+fn badge(book: &Book) -> String {
+    let class = match book.get_available() {
+        true => "available",
+        false => "unavailable",
+    };
+    format!(
+        "<span class=\"badge {}\">{}</span>",
+        class,
+        escape(&book.get_status().to_string())
+    )
+}
+
+fn page(title: &str, root: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <nav><a href=\"{root}index.html\">All books</a>\
+         <a href=\"{root}authors.html\">Authors</a>\
+         <a href=\"{root}genres.html\">Genres</a></nav>\n\
+         <h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape(title),
+        root = root,
+        body = body,
+    )
+}
+
+fn book_table(books: &[&Book], links: &Links) -> String {
+    let mut html = String::from(
+        "<table>\n<tr><th>Title</th><th>Author</th><th>Year</th><th>Genre</th><th>Status</th></tr>\n",
+    );
+    for book in books.iter() {
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{}\">{}</a></td>\
+             <td><a href=\"authors.html#{}\">{}</a></td><td>{}</td>\
+             <td><a href=\"genres.html#{}\">{}</a></td><td>{}</td></tr>",
+            links.file(book),
+            escape(book.get_title()),
+            links.author(book),
+            escape(book.get_author()),
+            book.get_publication_year(),
+            links.genre(book),
+            escape(&book.get_genre().to_string()),
+            badge(book),
+        );
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn book_page(book: &Book, links: &Links) -> String {
+    let mut body = String::from("<dl>\n");
+    let mut field = |name: &str, value: String| {
+        let _ = writeln!(body, "<dt>{}</dt><dd>{}</dd>", name, value);
+    };
+    field(
+        "Author",
+        format!(
+            "<a href=\"../authors.html#{}\">{}</a>",
+            links.author(book),
+            escape(book.get_author())
+        ),
+    );
+    field("ISBN", escape(book.get_isbn().as_str()));
+    field("Year", book.get_publication_year().to_string());
+    field(
+        "Genre",
+        format!(
+            "<a href=\"../genres.html#{}\">{}</a>",
+            links.genre(book),
+            escape(&book.get_genre().to_string())
+        ),
+    );
+    if let Some(series) = book.get_series() {
+        field("Series", escape(&series.to_string()));
+    }
+    if !book.get_tags().is_empty() {
+        field("Tags", escape(&book.get_tags().join(", ")));
+    }
+    field("Status", badge(book));
+    body.push_str("</dl>\n");
+    page(book.get_title(), "../", &body)
+}
+
+/// Renders one page listing every group under its own heading, with the
+/// anchors in `anchors`.
+fn index_page(
+    title: &str,
+    groups: &BTreeMap<String, Vec<&Book>>,
+    anchors: &HashMap<String, String>,
+    links: &Links,
+) -> String {
+    let mut body = String::from("<ul>\n");
+    for name in groups.keys() {
+        let _ = writeln!(
+            body,
+            "<li><a href=\"#{}\">{}</a> ({})</li>",
+            anchors[name],
+            escape(name),
+            groups[name].len()
+        );
+    }
+    body.push_str("</ul>\n");
+    for (name, books) in groups.iter() {
+        let _ = writeln!(body, "<h2 id=\"{}\">{}</h2>", anchors[name], escape(name));
+        body.push_str(&book_table(books, links));
+    }
+    page(title, "", &body)
+}
+
+/// Writes `library` as a static HTML site into `dir`: an index of all books,
+/// one page per book, and author and genre indexes.
+pub fn export(library: &Library, dir: &Path) -> Result<(), io::Error> {
+    export_with_progress(library, dir, &mut |_, _| {})
+}
+
+/// Like `export`, calling `progress` with the book pages written so far and
+/// the number of books.
+pub fn export_with_progress(
+    library: &Library,
+    dir: &Path,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<(), io::Error> {
+    fs::create_dir_all(dir.join("books"))?;
+
+    let mut books: Vec<&Book> = library.get_books().iter().collect();
+    books.sort_by_key(|book| book.get_title().to_lowercase());
+    let links = Links::new(&books);
+
+    let mut by_author: BTreeMap<String, Vec<&Book>> = BTreeMap::new();
+    let mut by_genre: BTreeMap<String, Vec<&Book>> = BTreeMap::new();
+    for (i, book) in books.iter().enumerate() {
+        by_author
+            .entry(book.get_author().to_string())
+            .or_default()
+            .push(book);
+        by_genre
+            .entry(book.get_genre().to_string())
+            .or_default()
+            .push(book);
+        fs::write(dir.join(links.file(book)), book_page(book, &links))?;
+        progress(i + 1, books.len());
+    }
+
+    let available = books.iter().filter(|book| book.get_available()).count();
+    let index = format!(
+        "<p>{} books, {} available.</p>\n{}",
+        books.len(),
+        available,
+        book_table(&books, &links)
+    );
+    fs::write(
+        dir.join("index.html"),
+        page(&format!("{}'s Library", library.get_owner()), "", &index),
+    )?;
+    let authors = index_page("Authors", &by_author, &links.authors, &links);
+    fs::write(dir.join("authors.html"), authors)?;
+    let genres = index_page("Genres", &by_genre, &links.genres, &links);
+    fs::write(dir.join("genres.html"), genres)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_escaped_and_linked() {
+        let json = r#"{"owner": "Ana", "books": [
+            {"title": "Pride & <Prejudice>", "author": "Austen, \"Jane\"", "isbn": "9780141439518",
+             "publication_year": 1813, "genre": "Fiction", "status": "Available"},
+            {"title": "Untitled", "author": "???", "isbn": "n/a",
+             "publication_year": 0, "genre": "Other", "status": "Lost"}]}"#;
+        let library: Library = serde_json::from_str(json).unwrap();
+        let dir = std::env::temp_dir().join(format!("libros-{}-site", std::process::id()));
+        export(&library, &dir).unwrap();
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        let (index, pride, untitled) = (
+            read("index.html"),
+            read("books/9780141439518.html"),
+            read("books/n-a.html"),
+        );
+        let authors = read("authors.html");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(index.contains(
+            "<td><a href=\"books/9780141439518.html\">Pride &amp; &lt;Prejudice&gt;</a></td>"
+        ));
+        assert!(index.contains(
+            "<td><a href=\"authors.html#austen-jane\">Austen, &quot;Jane&quot;</a></td>"
+        ));
+        assert!(index.contains("<a href=\"books/n-a.html\">Untitled</a>"));
+        assert!(index.contains("<a href=\"authors.html#author\">???</a>"));
+        assert!(pride.contains("<title>Pride &amp; &lt;Prejudice&gt;</title>"));
+        assert!(pride.contains("<a href=\"../genres.html#fiction\">Fiction</a>"));
+        assert!(untitled.contains("<span class=\"badge unavailable\">Lost</span>"));
+        assert!(authors.contains("<h2 id=\"austen-jane\">Austen, &quot;Jane&quot;</h2>"));
+    }
+
+    #[test]
+    fn slugs_are_unique() {
+        let mut slugs = Slugs::default();
+        assert_eq!(slugs.unique("Smith, J.", "author"), "smith-j");
+        assert_eq!(slugs.unique("Smith J", "author"), "smith-j-2");
+        assert_eq!(slugs.unique("smith j", "author"), "smith-j-3");
+    }
+
+    #[test]
+    fn slugs_fall_back_when_empty() {
+        let mut slugs = Slugs::default();
+        assert_eq!(slugs.unique("?!", "author"), "author");
+        assert_eq!(slugs.unique("", "author"), "author-2");
+        assert_eq!(slugs.unique("Толстой", "author"), "толстой");
+    }
+}
@@ -0,0 +1,533 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// The languages the interface is translated into. English text is the key
+/// every catalog is looked up by.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Language {
+    English,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    /// The language the locale asks for, checking `LC_ALL`, `LC_MESSAGES`
+    /// and `LANG` in the order the C library does.
+    pub fn from_env() -> Language {
+        for var in ["LC_ALL", "LC_MESSAGES", "LANG"] {
+            match env::var(var) {
+                Ok(locale) if locale.starts_with("es") => return Language::Spanish,
+                Ok(locale) if !locale.is_empty() => return Language::English,
+                _ => {}
+            }
+        }
+        Language::English
+    }
+
+    /// The language's name in itself, so it can be found without reading
+    /// the current one.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => &[],
+            Language::Spanish => SPANISH,
+        }
+    }
+}
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::ALL[LANGUAGE.load(Ordering::Relaxed) as usize]
+}
+
+/// `text` in the current language, or as it is when there's no translation.
+pub fn t(text: &'static str) -> &'static str {
+    language()
+        .catalog()
+        .iter()
+        .find(|(english, _)| *english == text)
+        .map_or(text, |(_, translated)| translated)
+}
+
+/// Replaces each `{}` in `template` with the next argument.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+/// Like `format!`, but translates the template first. Only `{}` is
+/// supported, and arguments fill it in order.
+macro_rules! tr {
+    ($template:literal $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::fill($crate::i18n::t($template), &[$(&$arg),*])
+    };
+}
+pub(crate) use tr;
+
+const SPANISH: &[(&str, &str)] = &[
+    // Catalog
+    ("Fiction", "Ficción"),
+    ("Non-Fiction", "No ficción"),
+    ("Science Fiction", "Ciencia ficción"),
+    ("Mystery", "Misterio"),
+    ("Other", "Otro"),
+    ("Available", "Disponible"),
+    ("Checked Out", "Prestado"),
+    ("Lost", "Perdido"),
+    ("Title", "Título"),
+    ("Author", "Autor"),
+    ("ISBN", "ISBN"),
+    ("Year", "Año"),
+    ("Genre", "Género"),
+    ("Status", "Estado"),
+    ("Series", "Serie"),
+    ("Tags", "Etiquetas"),
+    ("Genre: {}", "Género: {}"),
+    ("Status: {}", "Estado: {}"),
+    ("Year: {}-{}", "Año: {}-{}"),
+    ("Year: from {}", "Año: desde {}"),
+    ("Year: until {}", "Año: hasta {}"),
+    // Library errors
+    ("Book is not available!", "¡El libro no está disponible!"),
+    ("Book is not checked out!", "¡El libro no está prestado!"),
+    ("Book not found!", "¡No se encontró el libro!"),
+    (
+        "Book is available, check it out!",
+        "¡El libro está disponible, pídelo prestado!",
+    ),
+    (
+        "Patron already has this book!",
+        "¡El lector ya tiene este libro!",
+    ),
+    (
+        "Patron is already waiting for this book!",
+        "¡El lector ya está esperando este libro!",
+    ),
+    (
+        "Couldn't save config: {}",
+        "No se pudo guardar la configuración: {}",
+    ),
+    ("Couldn't read {}: {}", "No se pudo leer {}: {}"),
+    (
+        "Settings won't be saved until {} is fixed",
+        "La configuración no se guardará hasta que se corrija {}",
+    ),
+    (
+        "Unknown theme {}, using the default",
+        "Tema desconocido {}, se usa el predeterminado",
+    ),
+    (
+        "Ignoring the key binding for '{}'",
+        "Se ignora la tecla asignada a '{}'",
+    ),
+    (
+        "Ignoring key_bindings, which should be an object",
+        "Se ignora key_bindings, que debería ser un objeto",
+    ),
+    (
+        "Key {} for '{}' should be a string",
+        "La tecla {} de '{}' debería ser un texto",
+    ),
+    // ISBN errors
+    (
+        "ISBN must have 10 or 13 digits, found {}",
+        "El ISBN debe tener 10 o 13 dígitos, tiene {}",
+    ),
+    ("ISBN can't contain '{}'", "El ISBN no puede contener '{}'"),
+    (
+        "ISBN check digit doesn't match",
+        "El dígito de control del ISBN no coincide",
+    ),
+    (
+        "Only 978 ISBNs have an ISBN-10 form",
+        "Solo los ISBN 978 tienen forma ISBN-10",
+    ),
+    // Popups
+    ("(unbound)", "(sin asignar)"),
+    (
+        "Loading library, press {} and wait...",
+        "Cargando la biblioteca, pulsa {} y espera...",
+    ),
+    (
+        "You can cancel by pressing {}",
+        "Puedes cancelar pulsando {}",
+    ),
+    ("Load", "Cargar"),
+    ("Cancel", "Cancelar"),
+    ("New Library", "Nueva biblioteca"),
+    (
+        "Enter the owner of the library:",
+        "Escribe el dueño de la biblioteca:",
+    ),
+    (
+        "Are you sure you want to exit? ({}/{})",
+        "¿Seguro que quieres salir? ({}/{})",
+    ),
+    (
+        "Exiting Library Management Tool",
+        "Saliendo del gestor de bibliotecas",
+    ),
+    ("Yes", "Sí"),
+    ("No", "No"),
+    // Main screen
+    (
+        "Library Management Tool - {}'s Library",
+        "Gestor de bibliotecas - Biblioteca de {}",
+    ),
+    ("Loan", "Préstamo"),
+    ("Holds", "Reservas"),
+    ("History", "Historial"),
+    (" (overdue)", " (vencido)"),
+    ("{} since {}, due {}{}", "{} desde el {}, vence el {}{}"),
+    ("not on loan", "no está prestado"),
+    ("none", "ninguna"),
+    ("{} waiting", "{} en espera"),
+    ("  {}. {} (since {})", "  {}. {} (desde el {})"),
+    ("  {} (since {})", "  {} (desde el {})"),
+    (
+        "borrowed {} time(s), last by {} on {}",
+        "prestado {} vez/veces, la última a {} el {}",
+    ),
+    ("never borrowed", "nunca prestado"),
+    ("Details", "Detalles"),
+    ("No book selected", "Ningún libro seleccionado"),
+    ("All", "Todos"),
+    ("From", "Desde"),
+    ("To", "Hasta"),
+    ("Filters", "Filtros"),
+    ("Invalid {} - Query: ", "{} no válido - Consulta: "),
+    ("Searching by {} - Query: ", "Buscando por {} - Consulta: "),
+    ("Home", "Inicio"),
+    ("Search", "Buscar"),
+    ("Filter", "Filtrar"),
+    ("Check Out", "Préstamo"),
+    (
+        "Switching search criteria ({})",
+        "Cambiando el criterio de búsqueda ({})",
+    ),
+    ("{} of {} books", "{} de {} libros"),
+    (
+        "Checking out '{}', by {}, for {}",
+        "Prestando '{}', de {}, a {}",
+    ),
+    ("Filters: {}", "Filtros: {}"),
+    ("OK", "Listo"),
+    // Key hints
+    ("to move", "para moverte"),
+    ("to open", "para abrir"),
+    ("to choose", "para elegir"),
+    ("(1-6) to sort", "(1-6) para ordenar"),
+    ("columns", "columnas"),
+    ("to filter", "para filtrar"),
+    ("to search", "para buscar"),
+    ("to quit", "para salir"),
+    ("help", "ayuda"),
+    ("commands", "comandos"),
+    (
+        "(1-6) to show/hide a column",
+        "(1-6) para mostrar/ocultar una columna",
+    ),
+    ("done", "listo"),
+    ("Type to search", "Escribe para buscar"),
+    ("to pick a result", "para elegir un resultado"),
+    (
+        "to switch to search criteria selection",
+        "para elegir el criterio de búsqueda",
+    ),
+    ("main screen", "pantalla principal"),
+    ("by Author", "por autor"),
+    ("by Title", "por título"),
+    ("by ISBN", "por ISBN"),
+    ("to switch to query input", "para escribir la consulta"),
+    ("to pick a filter", "para elegir un filtro"),
+    ("to change it", "para cambiarlo"),
+    ("(0-9) to type a year", "(0-9) para escribir un año"),
+    ("to clear all", "para borrar todos"),
+    ("to check out book", "para prestar el libro"),
+    ("to return", "para devolverlo"),
+    ("to place a hold", "para reservarlo"),
+    ("to go back", "para volver"),
+    // Help
+    ("Navigation", "Navegación"),
+    ("Actions", "Acciones"),
+    ("Editing", "Edición"),
+    ("General", "General"),
+    (
+        "Sort by a column, again to reverse",
+        "Ordenar por una columna, otra vez para invertir",
+    ),
+    ("Show or hide a column", "Mostrar u ocultar una columna"),
+    ("Type a year", "Escribir un año"),
+    ("Move the cursor", "Mover el cursor"),
+    ("Move by word", "Moverse por palabras"),
+    ("Go to the start or end", "Ir al principio o al final"),
+    ("Delete a character", "Borrar un carácter"),
+    ("Delete the previous word", "Borrar la palabra anterior"),
+    (
+        "Delete to the start or end",
+        "Borrar hasta el principio o el final",
+    ),
+    ("Loading", "Carga"),
+    ("Columns", "Columnas"),
+    ("Search Criteria", "Criterio de búsqueda"),
+    ("Exit", "Salir"),
+    ("Help", "Ayuda"),
+    ("Command Palette", "Paleta de comandos"),
+    ("Help - {}", "Ayuda - {}"),
+    ("({}) close", "({}) cerrar"),
+    ("Scroll up", "Subir"),
+    ("Scroll down", "Bajar"),
+    ("Previous command", "Comando anterior"),
+    ("Next command", "Comando siguiente"),
+    ("Move up", "Subir"),
+    ("Move down", "Bajar"),
+    ("Page up", "Página anterior"),
+    ("Page down", "Página siguiente"),
+    ("Go to the top", "Ir al principio"),
+    ("Go to the bottom", "Ir al final"),
+    ("Next filter", "Filtro siguiente"),
+    ("Previous filter", "Filtro anterior"),
+    ("Next value for the filter", "Valor siguiente del filtro"),
+    ("Previous value for the filter", "Valor anterior del filtro"),
+    ("Run the command", "Ejecutar el comando"),
+    ("Load the library", "Cargar la biblioteca"),
+    ("Create the library", "Crear la biblioteca"),
+    ("Check out the book", "Prestar el libro"),
+    ("Close the filter bar", "Cerrar la barra de filtros"),
+    ("Open the selected book", "Abrir el libro seleccionado"),
+    (
+        "Check out and place holds for this patron",
+        "Prestar y reservar para este lector",
+    ),
+    ("Search the catalog", "Buscar en el catálogo"),
+    ("Filter the catalog", "Filtrar el catálogo"),
+    ("Stop choosing columns", "Dejar de elegir columnas"),
+    ("Choose the visible columns", "Elegir las columnas visibles"),
+    ("Pick what to search by", "Elegir por qué buscar"),
+    ("Type the query", "Escribir la consulta"),
+    ("Search by title", "Buscar por título"),
+    ("Search by author", "Buscar por autor"),
+    ("Search by ISBN", "Buscar por ISBN"),
+    ("Clear all filters", "Borrar todos los filtros"),
+    ("Return the book", "Devolver el libro"),
+    ("Place a hold", "Reservar"),
+    ("Exit and save", "Salir y guardar"),
+    ("Stay", "Quedarse"),
+    (
+        "Stop loading, or exit without loading",
+        "Dejar de cargar, o salir sin cargar",
+    ),
+    ("Close help", "Cerrar la ayuda"),
+    ("Close the palette", "Cerrar la paleta"),
+    (
+        "Go back to the main screen",
+        "Volver a la pantalla principal",
+    ),
+    ("Go back to the search", "Volver a la búsqueda"),
+    ("Quit", "Salir"),
+    (
+        "Show or hide the key bindings",
+        "Mostrar u ocultar las teclas",
+    ),
+    ("Open the command palette", "Abrir la paleta de comandos"),
+    // Command palette
+    (
+        "Directory for the HTML site",
+        "Directorio para el sitio HTML",
+    ),
+    (
+        "Directory for the OPDS feeds",
+        "Directorio para los feeds OPDS",
+    ),
+    (
+        "Calibre library or metadata.db",
+        "Biblioteca de Calibre o metadata.db",
+    ),
+    ("Library file", "Archivo de la biblioteca"),
+    (
+        "Patron (empty for the owner)",
+        "Lector (vacío para el dueño)",
+    ),
+    (
+        "Export the catalog as HTML",
+        "Exportar el catálogo como HTML",
+    ),
+    (
+        "Export the catalog as OPDS feeds",
+        "Exportar el catálogo como feeds OPDS",
+    ),
+    ("Import from Calibre", "Importar desde Calibre"),
+    ("Switch library", "Cambiar de biblioteca"),
+    ("Change the patron", "Cambiar de lector"),
+    (
+        "Checkouts and holds are now for {}",
+        "Los préstamos y reservas son ahora para {}",
+    ),
+    ("Theme: {}", "Tema: {}"),
+    ("Default", "Predeterminado"),
+    ("Color blind", "Para daltónicos"),
+    ("High contrast", "Alto contraste"),
+    ("Monochrome", "Monocromo"),
+    ("Key bindings: {}", "Teclas: {}"),
+    ("Language: {}", "Idioma: {}"),
+    ("Theme set to {}", "Tema cambiado a {}"),
+    ("Key bindings set to {}", "Teclas cambiadas a {}"),
+    ("Language set to {}", "Idioma cambiado a {}"),
+    ("Wrote the HTML site to {}", "Sitio HTML escrito en {}"),
+    ("Wrote {} feed(s) to {}", "{} feed(s) escrito(s) en {}"),
+    ("Couldn't export: {}", "No se pudo exportar: {}"),
+    ("Couldn't import: {}", "No se pudo importar: {}"),
+    (
+        "Couldn't save library: {}",
+        "No se pudo guardar la biblioteca: {}",
+    ),
+    ("Couldn't open {}: {}", "No se pudo abrir {}: {}"),
+    (
+        "the current library has unsaved changes",
+        "la biblioteca actual tiene cambios sin guardar",
+    ),
+    (
+        "Imported {} book(s), merged {}, skipped {}",
+        "{} libro(s) importado(s), {} combinado(s), {} omitido(s)",
+    ),
+    ("Opened {}", "Abierto {}"),
+    (
+        "Creating a new library at {}",
+        "Creando una biblioteca nueva en {}",
+    ),
+    // Reports
+    (
+        "Show the collection statistics",
+        "Mostrar las estadísticas de la colección",
+    ),
+    ("{} books, {} available", "{} libros, {} disponibles"),
+    ("Books by genre", "Libros por género"),
+    ("Books by status", "Libros por estado"),
+    ("{} of {} available", "{} de {} disponibles"),
+    ("Books by decade", "Libros por década"),
+    ("Most borrowed", "Más prestados"),
+    ("No loans yet", "Todavía no hay préstamos"),
+    (
+        "Checkouts per month ({} in total)",
+        "Préstamos por mes ({} en total)",
+    ),
+    ("Jan", "ene"),
+    ("Feb", "feb"),
+    ("Mar", "mar"),
+    ("Apr", "abr"),
+    ("May", "may"),
+    ("Jun", "jun"),
+    ("Jul", "jul"),
+    ("Aug", "ago"),
+    ("Sep", "sep"),
+    ("Oct", "oct"),
+    ("Nov", "nov"),
+    ("Dec", "dic"),
+    // Layout
+    ("{}'s Library", "Biblioteca de {}"),
+    ("  {}, due {}", "  {}, vence el {}"),
+    ("No books on loan", "No hay libros prestados"),
+    ("On Loan ({})", "Prestados ({})"),
+    (
+        "Terminal too small\n{}x{}, needs {}x{}",
+        "Terminal demasiado pequeña\n{}x{}, necesita {}x{}",
+    ),
+    // Tabs
+    ("Catalog", "Catálogo"),
+    ("Loans", "Préstamos"),
+    ("Patrons", "Lectores"),
+    ("Reports", "Informes"),
+    ("tabs", "pestañas"),
+    ("catalog", "catálogo"),
+    ("Next tab", "Pestaña siguiente"),
+    ("Previous tab", "Pestaña anterior"),
+    ("Go back to the catalog", "Volver al catálogo"),
+    ("Patron", "Lector"),
+    ("Checked out", "Prestado el"),
+    ("Due", "Vence"),
+    ("On loan", "Prestados"),
+    ("Overdue", "Vencidos"),
+    ("Borrowed", "Préstamos"),
+    ("No patron selected", "Ningún lector seleccionado"),
+    ("{} on loan, {} overdue", "{} prestados, {} vencidos"),
+    ("{} patrons", "{} lectores"),
+    // Notifications
+    ("Info", "Info"),
+    ("Warning", "Aviso"),
+    ("Error", "Error"),
+    ("Messages", "Mensajes"),
+    ("messages", "mensajes"),
+    ("No messages yet", "Aún no hay mensajes"),
+    ("Show recent messages", "Mostrar los mensajes recientes"),
+    ("Close the messages", "Cerrar los mensajes"),
+    ("Checked out \"{}\"", "Prestado \"{}\""),
+    ("Returned \"{}\"", "Devuelto \"{}\""),
+    ("Placed a hold on \"{}\"", "Reservado \"{}\""),
+    // Background tasks
+    ("Loading the library", "Cargando la biblioteca"),
+    ("Opening the library", "Abriendo la biblioteca"),
+    ("Importing from Calibre", "Importando desde Calibre"),
+    ("Exporting the HTML site", "Exportando el sitio HTML"),
+    ("Exporting the OPDS feeds", "Exportando los feeds OPDS"),
+    ("Wait until this is done: {}", "Espera a que termine: {}"),
+    ("The task failed: {}", "La tarea falló: {}"),
+    ("Cancelled: {}", "Cancelado: {}"),
+    (
+        "Restored unsaved changes from {}",
+        "Se recuperaron los cambios sin guardar de {}",
+    ),
+    ("Unsaved changes", "Cambios sin guardar"),
+    // Command line
+    ("Couldn't load {}: {}", "No se pudo cargar {}: {}"),
+    (
+        "No library found at {}, run libros once to create it",
+        "No hay ninguna biblioteca en {}, ejecuta libros una vez para crearla",
+    ),
+    (
+        "Unsaved changes in {} are left out; start libros to restore them",
+        "Los cambios sin guardar de {} no se incluyen; inicia libros para recuperarlos",
+    ),
+    (
+        "Unsaved changes are waiting in {}; start libros to restore them before importing",
+        "Hay cambios sin guardar en {}; inicia libros para recuperarlos antes de importar",
+    ),
+    ("Imported {} book(s)", "{} libro(s) importado(s)"),
+    (
+        "Merged {} record(s) into existing books",
+        "{} registro(s) combinado(s) con libros existentes",
+    ),
+    ("merged: {}", "combinado: {}"),
+    ("Skipped {} record(s)", "{} registro(s) omitido(s)"),
+    ("skipped: {} ({})", "omitido: {} ({})"),
+    (
+        "Wrote {} book page(s) to {}",
+        "{} página(s) de libros escrita(s) en {}",
+    ),
+    (
+        "Serving OPDS catalog on http://{}/index.xml",
+        "Sirviendo el catálogo OPDS en http://{}/index.xml",
+    ),
+];
@@ -0,0 +1,263 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Position, Rect},
+    style::Style,
+    text::Span,
+    Frame,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// A single-line text field with a cursor.
+///
+/// The cursor is a byte offset that always sits on a grapheme boundary, so
+/// editing never splits an accented letter or an emoji, and the on-screen
+/// cursor position is measured in terminal columns so wide characters line up.
+#[derive(Debug, Default, Clone)]
+pub struct TextInput {
+    value: String,
+    cursor: usize,
+}
+
+impl TextInput {
+    pub fn new() -> TextInput {
+        TextInput::default()
+    }
+
+    /// A field holding `value`, with the cursor at the end.
+    pub fn with_value(value: &str) -> TextInput {
+        TextInput {
+            value: value.to_string(),
+            cursor: value.len(),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.value.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Inserts pasted text at the cursor; line breaks become spaces.
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .chars()
+            .filter(|c| *c != '\r')
+            .map(|c| if c == '\n' { ' ' } else { c })
+            .collect();
+        self.value.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    fn prev_boundary(&self, from: usize) -> usize {
+        self.value[..from]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, from: usize) -> usize {
+        self.value[from..]
+            .graphemes(true)
+            .next()
+            .map_or(from, |g| from + g.len())
+    }
+
+    /// Start of the word before `from`, skipping any whitespace first.
+    fn prev_word(&self, from: usize) -> usize {
+        let mut i = from;
+        while i > 0 && self.grapheme_before(i).trim().is_empty() {
+            i = self.prev_boundary(i);
+        }
+        while i > 0 && !self.grapheme_before(i).trim().is_empty() {
+            i = self.prev_boundary(i);
+        }
+        i
+    }
+
+    /// End of the word after `from`, skipping any whitespace first.
+    fn next_word(&self, from: usize) -> usize {
+        let len = self.value.len();
+        let mut i = from;
+        while i < len && self.grapheme_after(i).trim().is_empty() {
+            i = self.next_boundary(i);
+        }
+        while i < len && !self.grapheme_after(i).trim().is_empty() {
+            i = self.next_boundary(i);
+        }
+        i
+    }
+
+    fn grapheme_before(&self, i: usize) -> &str {
+        &self.value[self.prev_boundary(i)..i]
+    }
+
+    fn grapheme_after(&self, i: usize) -> &str {
+        &self.value[i..self.next_boundary(i)]
+    }
+
+    fn delete(&mut self, from: usize, to: usize) {
+        self.value.replace_range(from..to, "");
+        self.cursor = from;
+    }
+
+    /// Applies an editing or movement key. Returns whether the key was used;
+    /// callers handle the rest (Enter, Esc, Tab...).
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        // AltGr reaches some terminals (Windows in particular) as Ctrl+Alt;
+        // the character is the one that was typed, e.g. '@' or '€'.
+        let (ctrl, alt) = match key.code {
+            KeyCode::Char(_) if ctrl && alt => (false, false),
+            _ => (ctrl, alt),
+        };
+        match key.code {
+            KeyCode::Left if ctrl || alt => self.cursor = self.prev_word(self.cursor),
+            KeyCode::Right if ctrl || alt => self.cursor = self.next_word(self.cursor),
+            KeyCode::Char('b') if alt => self.cursor = self.prev_word(self.cursor),
+            KeyCode::Char('f') if alt => self.cursor = self.next_word(self.cursor),
+            KeyCode::Left => self.cursor = self.prev_boundary(self.cursor),
+            KeyCode::Right => self.cursor = self.next_boundary(self.cursor),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.len(),
+            KeyCode::Char('e') if ctrl => self.cursor = self.value.len(),
+            KeyCode::Backspace if ctrl || alt => {
+                self.delete(self.prev_word(self.cursor), self.cursor)
+            }
+            KeyCode::Char('w') if ctrl => self.delete(self.prev_word(self.cursor), self.cursor),
+            KeyCode::Char('u') if ctrl => self.delete(0, self.cursor),
+            KeyCode::Char('k') if ctrl => self.delete(self.cursor, self.value.len()),
+            KeyCode::Backspace => self.delete(self.prev_boundary(self.cursor), self.cursor),
+            KeyCode::Delete => self.delete(self.cursor, self.next_boundary(self.cursor)),
+            KeyCode::Char(c) if !ctrl && !alt => self.insert_char(c),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Terminal columns between the start of the text and the cursor.
+    pub fn cursor_width(&self) -> u16 {
+        self.value[..self.cursor].width() as u16
+    }
+
+    /// Renders the value in `area`, scrolled so the cursor stays visible,
+    /// and places the terminal cursor when `focused`.
+    pub fn render(&self, frame: &mut Frame, area: Rect, style: Style, focused: bool) {
+        // Drop graphemes from the front until the cursor fits.
+        let mut start = 0;
+        while start < self.cursor
+            && self.value[start..self.cursor].width() as u16 >= area.width.max(1)
+        {
+            start = self.next_boundary(start);
+        }
+        frame.render_widget(Span::styled(&self.value[start..], style), area);
+        if focused {
+            let x = area.x + self.value[start..self.cursor].width() as u16;
+            frame.set_cursor_position(Position::new(x, area.y));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(input: &mut TextInput, c: char, modifiers: KeyModifiers) -> bool {
+        input.handle_key(KeyEvent::new(KeyCode::Char(c), modifiers))
+    }
+
+    fn key(input: &mut TextInput, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        input.handle_key(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn jumps_and_deletes_words() {
+        let mut input = TextInput::with_value("  the great  gatsby ");
+        key(&mut input, KeyCode::Left, KeyModifiers::CONTROL);
+        assert_eq!(input.cursor_width(), 13);
+        key(&mut input, KeyCode::Left, KeyModifiers::ALT);
+        assert_eq!(input.cursor_width(), 6);
+        press(&mut input, 'b', KeyModifiers::ALT);
+        assert_eq!(input.cursor_width(), 2);
+        press(&mut input, 'f', KeyModifiers::ALT);
+        assert_eq!(input.cursor_width(), 5);
+        key(&mut input, KeyCode::Right, KeyModifiers::CONTROL);
+        assert_eq!(input.cursor_width(), 11);
+        press(&mut input, 'w', KeyModifiers::CONTROL);
+        assert_eq!(input.value(), "  the   gatsby ");
+        key(&mut input, KeyCode::Backspace, KeyModifiers::ALT);
+        assert_eq!(input.value(), "    gatsby ");
+        assert_eq!(input.cursor_width(), 2);
+        press(&mut input, 'k', KeyModifiers::CONTROL);
+        assert_eq!(input.value(), "  ");
+    }
+
+    #[test]
+    fn moves_over_wide_and_combining_graphemes() {
+        // "e" with a combining acute accent, then a wide CJK character.
+        let mut input = TextInput::with_value("e\u{301}本x");
+        assert_eq!(input.cursor_width(), 4);
+        key(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        key(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        assert_eq!(input.cursor_width(), 1);
+        key(&mut input, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(input.value(), "本x");
+        key(&mut input, KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!(input.value(), "x");
+        input.insert_str("家\n");
+        assert_eq!(input.value(), "家 x");
+        assert_eq!(input.cursor_width(), 3);
+    }
+
+    #[test]
+    fn backspace_and_delete_stop_at_the_edges() {
+        let mut input = TextInput::with_value("ab");
+        key(&mut input, KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!(input.value(), "ab");
+        key(&mut input, KeyCode::Home, KeyModifiers::NONE);
+        key(&mut input, KeyCode::Backspace, KeyModifiers::NONE);
+        key(&mut input, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut input, 'w', KeyModifiers::CONTROL);
+        assert_eq!((input.value(), input.cursor_width()), ("ab", 0));
+        key(&mut input, KeyCode::Delete, KeyModifiers::NONE);
+        key(&mut input, KeyCode::Delete, KeyModifiers::NONE);
+        key(&mut input, KeyCode::Delete, KeyModifiers::NONE);
+        key(&mut input, KeyCode::Right, KeyModifiers::NONE);
+        assert_eq!((input.value(), input.cursor_width()), ("", 0));
+    }
+
+    #[test]
+    fn inserts_altgr_characters() {
+        let mut input = TextInput::new();
+        assert!(press(
+            &mut input,
+            '@',
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        ));
+        assert!(press(
+            &mut input,
+            '€',
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        ));
+        assert_eq!(input.value(), "@€");
+    }
+
+    #[test]
+    fn leaves_other_shortcuts_alone() {
+        let mut input = TextInput::with_value("ab");
+        assert!(!press(&mut input, 'p', KeyModifiers::CONTROL));
+        assert!(!press(&mut input, 'x', KeyModifiers::ALT));
+        assert!(press(&mut input, 'a', KeyModifiers::CONTROL));
+        assert_eq!((input.value(), input.cursor_width()), ("ab", 0));
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::{t, tr};
+
+#[derive(Debug, PartialEq)]
+pub enum IsbnError {
+    InvalidLength(usize),
+    InvalidCharacter(char),
+    InvalidChecksum,
+    NoIsbn10,
+}
+
+impl fmt::Display for IsbnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IsbnError::InvalidLength(len) => {
+                f.write_str(&tr!("ISBN must have 10 or 13 digits, found {}", len))
+            }
+            IsbnError::InvalidCharacter(c) => f.write_str(&tr!("ISBN can't contain '{}'", c)),
+            IsbnError::InvalidChecksum => f.write_str(t("ISBN check digit doesn't match")),
+            IsbnError::NoIsbn10 => f.write_str(t("Only 978 ISBNs have an ISBN-10 form")),
+        }
+    }
+}
+
+impl std::error::Error for IsbnError {}
+
+/// An ISBN, stored as 13 digits once validated.
+///
+/// Parsing accepts ISBN-10 or ISBN-13, with or without hyphens and spaces,
+/// so differently formatted inputs for the same book compare equal. Values
+/// read from a library file that don't validate are kept as they were, so
+/// older libraries still load.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Isbn(Repr);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Repr {
+    Valid(String),
+    /// Not a valid ISBN, exactly as found.
+    Raw(String),
+}
+
+fn isbn10_check_digit(digits: &str) -> char {
+    let sum: u32 = digits
+        .chars()
+        .zip((2..=10).rev())
+        .map(|(c, weight)| c.to_digit(10).unwrap() * weight)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from_digit(check, 10).unwrap(),
+    }
+}
+
+fn isbn13_check_digit(digits: &str) -> char {
+    let sum: u32 = digits
+        .chars()
+        .zip([1, 3].iter().cycle())
+        .map(|(c, weight)| c.to_digit(10).unwrap() * weight)
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap()
+}
+
+impl Isbn {
+    pub fn parse(value: &str) -> Result<Isbn, IsbnError> {
+        let compact: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let len = compact.chars().count();
+        if len != 10 && len != 13 {
+            return Err(IsbnError::InvalidLength(len));
+        }
+        for (i, c) in compact.chars().enumerate() {
+            let is_isbn10_check = len == 10 && i == 9 && c == 'X';
+            if !c.is_ascii_digit() && !is_isbn10_check {
+                return Err(IsbnError::InvalidCharacter(c));
+            }
+        }
+
+        let (body, check) = compact.split_at(len - 1);
+        if len == 10 {
+            if isbn10_check_digit(body) != check.chars().next().unwrap() {
+                return Err(IsbnError::InvalidChecksum);
+            }
+            let body = format!("978{}", body);
+            let check = isbn13_check_digit(&body);
+            Ok(Isbn(Repr::Valid(format!("{}{}", body, check))))
+        } else {
+            if isbn13_check_digit(body) != check.chars().next().unwrap() {
+                return Err(IsbnError::InvalidChecksum);
+            }
+            Ok(Isbn(Repr::Valid(compact)))
+        }
+    }
+
+    /// The 13-digit form, without hyphens, or the raw value.
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Valid(isbn) | Repr::Raw(isbn) => isbn,
+        }
+    }
+
+    /// Whether this is a real ISBN rather than a value kept as it was found.
+    pub fn is_valid(&self) -> bool {
+        matches!(self.0, Repr::Valid(_))
+    }
+
+    /// The 10-digit form, which only exists for the 978 prefix.
+    pub fn to_isbn10(&self) -> Result<String, IsbnError> {
+        let isbn = match &self.0 {
+            Repr::Valid(isbn) => isbn,
+            Repr::Raw(raw) => return Err(Isbn::parse(raw).unwrap_err()),
+        };
+        match isbn.strip_prefix("978") {
+            Some(rest) => {
+                let body = &rest[..9];
+                Ok(format!("{}{}", body, isbn10_check_digit(body)))
+            }
+            None => Err(IsbnError::NoIsbn10),
+        }
+    }
+}
+
+impl fmt::Display for Isbn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Isbn {
+    type Err = IsbnError;
+
+    fn from_str(s: &str) -> Result<Isbn, IsbnError> {
+        Isbn::parse(s)
+    }
+}
+
+impl From<String> for Isbn {
+    fn from(value: String) -> Isbn {
+        Isbn::parse(&value).unwrap_or(Isbn(Repr::Raw(value)))
+    }
+}
+
+impl From<Isbn> for String {
+    fn from(isbn: Isbn) -> String {
+        match isbn.0 {
+            Repr::Valid(isbn) | Repr::Raw(isbn) => isbn,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits() {
+        assert_eq!(isbn10_check_digit("074327356"), '7');
+        assert_eq!(isbn10_check_digit("080442957"), 'X');
+        assert_eq!(isbn13_check_digit("978074327356"), '5');
+        assert_eq!(isbn13_check_digit("979109000000"), '1');
+    }
+
+    #[test]
+    fn parses_isbn13_in_any_format() {
+        let isbn = Isbn::parse("9780743273565").unwrap();
+        assert_eq!(isbn.as_str(), "9780743273565");
+        assert_eq!(Isbn::parse("978-0-7432-7356-5").unwrap(), isbn);
+        assert_eq!(Isbn::parse(" 978 0743273565 ").unwrap(), isbn);
+    }
+
+    #[test]
+    fn parses_isbn10_as_isbn13() {
+        let isbn = Isbn::parse("0-7432-7356-7").unwrap();
+        assert_eq!(isbn.as_str(), "9780743273565");
+        assert_eq!(Isbn::parse("080442957x").unwrap().as_str(), "9780804429573");
+    }
+
+    #[test]
+    fn rejects_invalid_isbns() {
+        assert_eq!(Isbn::parse("12345"), Err(IsbnError::InvalidLength(5)));
+        assert_eq!(Isbn::parse(""), Err(IsbnError::InvalidLength(0)));
+        assert_eq!(
+            Isbn::parse("97807432735X5"),
+            Err(IsbnError::InvalidCharacter('X'))
+        );
+        assert_eq!(
+            Isbn::parse("9780743273566"),
+            Err(IsbnError::InvalidChecksum)
+        );
+        assert_eq!(Isbn::parse("0743273568"), Err(IsbnError::InvalidChecksum));
+    }
+
+    #[test]
+    fn converts_to_isbn10() {
+        let isbn = Isbn::parse("9780804429573").unwrap();
+        assert_eq!(isbn.to_isbn10().unwrap(), "080442957X");
+        let isbn = Isbn::parse("9780743273565").unwrap();
+        assert_eq!(isbn.to_isbn10().unwrap(), "0743273567");
+        let isbn = Isbn::parse("9791090000001").unwrap();
+        assert_eq!(isbn.to_isbn10(), Err(IsbnError::NoIsbn10));
+    }
+
+    #[test]
+    fn keeps_invalid_values_from_files() {
+        let isbn: Isbn = serde_json::from_str("\"978-0743273566 (pbk)\"").unwrap();
+        assert_eq!(isbn.as_str(), "978-0743273566 (pbk)");
+        assert!(!isbn.is_valid());
+        assert!(isbn.to_isbn10().is_err());
+        assert_eq!(
+            serde_json::to_string(&isbn).unwrap(),
+            "\"978-0743273566 (pbk)\""
+        );
+        let isbn: Isbn = serde_json::from_str("\"0-7432-7356-7\"").unwrap();
+        assert_eq!(isbn.as_str(), "9780743273565");
+        assert!(isbn.is_valid());
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+
+/// Something a key can do. Which actions a key can trigger depends on the
+/// `KeyContext` it's pressed in.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Action {
+    Confirm,
+    Back,
+    Quit,
+    Yes,
+    No,
+    MoveUp,
+    MoveDown,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    Search,
+    Filter,
+    Columns,
+    ToggleSearchInput,
+    SearchByTitle,
+    SearchByAuthor,
+    SearchByIsbn,
+    NextField,
+    PrevField,
+    NextValue,
+    PrevValue,
+    ClearFilter,
+    ReturnBook,
+    PlaceHold,
+    BackToSearch,
+    Help,
+    Palette,
+    Dashboard,
+    NextTab,
+    PrevTab,
+    Messages,
+}
+
+/// Where a key is pressed; each context has its own set of actions, so the
+/// same key can mean different things on different screens.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum KeyContext {
+    Loading,
+    NewOwner,
+    Home,
+    Columns,
+    SearchInput,
+    SearchCriteria,
+    Filtering,
+    Book,
+    Exiting,
+    /// The Loans, Patrons and Reports tabs; the Catalog tab is `Home`.
+    Loans,
+    Patrons,
+    Reports,
+    /// The help overlay, over any other context.
+    Help,
+    /// The command palette, over the screen it was opened on.
+    Palette,
+    /// The message history, over any screen.
+    Messages,
+}
+
+impl KeyContext {
+    pub fn actions(self) -> &'static [Action] {
+        use Action::*;
+        match self {
+            KeyContext::Loading => &[Confirm, Back, Help],
+            KeyContext::NewOwner => &[Confirm, Help],
+            KeyContext::Home => &[
+                MoveUp, MoveDown, PageUp, PageDown, First, Last, Confirm, Columns, Filter, Search,
+                Dashboard, NextTab, PrevTab, Messages, Quit, Help, Palette,
+            ],
+            KeyContext::Columns => &[Columns, Back, Help, Palette],
+            KeyContext::SearchInput => &[
+                MoveUp,
+                MoveDown,
+                PageUp,
+                PageDown,
+                ToggleSearchInput,
+                Confirm,
+                Back,
+                Help,
+                Palette,
+            ],
+            KeyContext::SearchCriteria => &[
+                SearchByAuthor,
+                SearchByTitle,
+                SearchByIsbn,
+                ToggleSearchInput,
+                MoveUp,
+                MoveDown,
+                PageUp,
+                PageDown,
+                Confirm,
+                Back,
+                Messages,
+                Quit,
+                Help,
+                Palette,
+            ],
+            KeyContext::Filtering => &[
+                NextField,
+                PrevField,
+                NextValue,
+                PrevValue,
+                ClearFilter,
+                Filter,
+                Confirm,
+                Back,
+                Help,
+                Palette,
+            ],
+            KeyContext::Book => &[
+                Confirm,
+                ReturnBook,
+                PlaceHold,
+                BackToSearch,
+                Back,
+                Messages,
+                Quit,
+                Help,
+                Palette,
+            ],
+            KeyContext::Exiting => &[Yes, No, Help],
+            KeyContext::Loans => &[
+                MoveUp, MoveDown, PageUp, PageDown, First, Last, Confirm, ReturnBook, NextTab,
+                PrevTab, Back, Messages, Quit, Help, Palette,
+            ],
+            KeyContext::Patrons => &[
+                MoveUp, MoveDown, PageUp, PageDown, First, Last, Confirm, NextTab, PrevTab, Back,
+                Messages, Quit, Help, Palette,
+            ],
+            KeyContext::Reports => &[
+                Dashboard, NextTab, PrevTab, Back, Messages, Quit, Help, Palette,
+            ],
+            KeyContext::Help => &[MoveUp, MoveDown, PageUp, PageDown, First, Last, Help, Back],
+            KeyContext::Palette => &[MoveUp, MoveDown, Confirm, Back, Help],
+            KeyContext::Messages => &[
+                MoveUp, MoveDown, PageUp, PageDown, First, Last, Messages, Back, Help,
+            ],
+        }
+    }
+}
+
+/// A key with its modifiers, written in the config as e.g. `"q"`,
+/// `"ctrl+p"`, `"alt+<"`, `"enter"` or `"pagedown"`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+const KEY_NAMES: [(&str, KeyCode); 14] = [
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    pub fn to_event(self) -> KeyEvent {
+        KeyEvent::new(self.code, self.modifiers)
+    }
+
+    /// How the key is shown in hints, with arrows for the arrow keys.
+    pub fn symbol(&self) -> String {
+        let name = match self.code {
+            KeyCode::Up => "↑".to_string(),
+            KeyCode::Down => "↓".to_string(),
+            KeyCode::Left => "←".to_string(),
+            KeyCode::Right => "→".to_string(),
+            _ => return self.to_string(),
+        };
+        match self.modifiers.is_empty() {
+            true => name,
+            false => format!("{}+{}", modifier_prefix(self.modifiers), name),
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Key {
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        // Shift is already part of a character (`G`, `<`), and part of
+        // BackTab, so it only counts for the other keys.
+        if !matches!(event.code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers |= event.modifiers & KeyModifiers::SHIFT;
+        }
+        Key::new(event.code, modifiers)
+    }
+}
+
+fn modifier_prefix(modifiers: KeyModifiers) -> String {
+    let mut names = Vec::new();
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        names.push("ctrl");
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        names.push("alt");
+    }
+    if modifiers.contains(KeyModifiers::SHIFT) {
+        names.push("shift");
+    }
+    names.join("+")
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.modifiers.is_empty() {
+            write!(f, "{}+", modifier_prefix(self.modifiers))?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => match KEY_NAMES.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Key, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        // Split off modifiers, leaving a lone "+" as the key itself.
+        while let Some((prefix, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("Unknown modifier '{}' in key '{}'", prefix, s)),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let name = rest.to_lowercase();
+                match KEY_NAMES.iter().find(|(named, _)| *named == name) {
+                    Some((_, code)) => *code,
+                    None if name == "space" => KeyCode::Char(' '),
+                    None => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n) => KeyCode::F(n),
+                        None => return Err(format!("Unknown key '{}'", s)),
+                    },
+                }
+            }
+        };
+        // Terminals send shift+tab as BackTab and shifted letters as the
+        // capital, without the shift, so write them the way they arrive.
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Tab if shift => KeyCode::BackTab,
+            KeyCode::Char(c) if shift && c.is_alphabetic() => {
+                KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+            }
+            code => code,
+        };
+        if matches!(code, KeyCode::BackTab | KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Key, String> {
+        value.parse()
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> String {
+        key.to_string()
+    }
+}
+
+/// A starting set of bindings; `key_bindings` in the config overrides
+/// individual actions on top of it.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone, Copy)]
+pub enum Preset {
+    #[default]
+    Default,
+    Vim,
+    Emacs,
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Preset::Default => f.write_str(t("Default")),
+            Preset::Vim => f.write_str("Vim"),
+            Preset::Emacs => f.write_str("Emacs"),
+        }
+    }
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Default, Preset::Vim, Preset::Emacs];
+
+    fn bindings(self) -> Vec<(Action, &'static [&'static str])> {
+        use Action::*;
+        let mut bindings: Vec<(Action, &[&str])> = vec![
+            (Confirm, &["enter"]),
+            (Back, &["esc"]),
+            (Quit, &["q"]),
+            (Yes, &["y"]),
+            (No, &["n"]),
+            (MoveUp, &["up", "k"]),
+            (MoveDown, &["down", "j"]),
+            (PageUp, &["pageup"]),
+            (PageDown, &["pagedown"]),
+            (First, &["home", "g"]),
+            (Last, &["end", "G"]),
+            (Search, &["s"]),
+            (Filter, &["f"]),
+            (Columns, &["c"]),
+            (ToggleSearchInput, &["tab"]),
+            (SearchByTitle, &["t"]),
+            (SearchByAuthor, &["a"]),
+            (SearchByIsbn, &["i"]),
+            (NextField, &["tab", "down"]),
+            (PrevField, &["backtab", "up"]),
+            (NextValue, &["right"]),
+            (PrevValue, &["left"]),
+            (ClearFilter, &["c"]),
+            (ReturnBook, &["r"]),
+            (PlaceHold, &["h"]),
+            (BackToSearch, &["b"]),
+            (Help, &["?", "f1"]),
+            (Palette, &[":", "ctrl+p"]),
+            (Dashboard, &["d"]),
+            (NextTab, &["tab", "]"]),
+            (PrevTab, &["backtab", "["]),
+            (Messages, &["m"]),
+        ];
+        let overrides: Vec<(Action, &[&str])> = match self {
+            Preset::Default => vec![],
+            Preset::Vim => vec![
+                (MoveUp, &["k", "up"]),
+                (MoveDown, &["j", "down"]),
+                (PageUp, &["ctrl+b", "pageup"]),
+                (PageDown, &["ctrl+f", "pagedown"]),
+                (First, &["g", "home"]),
+                (Last, &["G", "end"]),
+                (Search, &["/"]),
+                (NextField, &["tab", "j", "down"]),
+                (PrevField, &["backtab", "k", "up"]),
+                (NextValue, &["l", "right"]),
+                (PrevValue, &["h", "left"]),
+            ],
+            Preset::Emacs => vec![
+                (Back, &["ctrl+g", "esc"]),
+                (Quit, &["ctrl+x"]),
+                (MoveUp, &["ctrl+p", "up"]),
+                (MoveDown, &["ctrl+n", "down"]),
+                (PageUp, &["alt+v", "pageup"]),
+                (PageDown, &["ctrl+v", "pagedown"]),
+                (First, &["alt+<", "home"]),
+                (Last, &["alt+>", "end"]),
+                (Search, &["ctrl+s"]),
+                (NextField, &["ctrl+n", "tab", "down"]),
+                (PrevField, &["ctrl+p", "backtab", "up"]),
+                (NextValue, &["ctrl+f", "right"]),
+                (PrevValue, &["ctrl+b", "left"]),
+                (Palette, &["alt+x", ":"]),
+            ],
+        };
+        for (action, keys) in overrides {
+            if let Some(binding) = bindings.iter_mut().find(|(a, _)| *a == action) {
+                binding.1 = keys;
+            }
+        }
+        bindings
+    }
+}
+
+/// The keys bound to every action.
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: HashMap<Action, Vec<Key>>,
+}
+
+impl Keymap {
+    pub fn new(preset: Preset, overrides: &BTreeMap<Action, Vec<Key>>) -> Keymap {
+        let mut bindings: HashMap<Action, Vec<Key>> = preset
+            .bindings()
+            .into_iter()
+            .map(|(action, keys)| (action, keys.iter().map(|k| k.parse().unwrap()).collect()))
+            .collect();
+        for (action, keys) in overrides {
+            bindings.insert(*action, keys.clone());
+        }
+        Keymap { bindings }
+    }
+
+    /// The action `event` triggers in `context`, if any.
+    pub fn action(&self, context: KeyContext, event: KeyEvent) -> Option<Action> {
+        let key = Key::from(event);
+        context
+            .actions()
+            .iter()
+            .find(|action| self.keys(**action).contains(&key))
+            .copied()
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// The key shown in hints for `action`: the first one bound.
+    pub fn key(&self, action: Action) -> Option<Key> {
+        self.keys(action).first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Key {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(parse("q"), Key::new(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(
+            parse("ctrl+p"),
+            Key::new(KeyCode::Char('p'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parse("alt++"),
+            Key::new(KeyCode::Char('+'), KeyModifiers::ALT)
+        );
+        assert_eq!(
+            parse("PageDown"),
+            Key::new(KeyCode::PageDown, KeyModifiers::NONE)
+        );
+        assert_eq!(parse("f1"), Key::new(KeyCode::F(1), KeyModifiers::NONE));
+        assert!("hyper+q".parse::<Key>().is_err());
+        assert!("nope".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn shifted_keys_match_what_the_terminal_sends() {
+        let backtab = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(parse("shift+tab"), Key::from(backtab));
+        assert_eq!(parse("shift+backtab"), Key::from(backtab));
+        assert_eq!(parse("backtab"), Key::from(backtab));
+        let capital = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(parse("shift+g"), Key::from(capital));
+        let up = KeyEvent::new(KeyCode::Up, KeyModifiers::SHIFT);
+        assert_eq!(parse("shift+up"), Key::from(up));
+    }
+}
@@ -1,31 +1,60 @@
 use std::fmt;
-use std::fs::{read_to_string, File};
-use std::io;
-use std::path::Path;
+use std::fs::File;
+use std::io::{self, Read};
 
+use chrono::{Days, NaiveDate};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-enum Genre {
+use crate::i18n::t;
+use crate::isbn::Isbn;
+
+/// How long a checked out book may be kept.
+const LOAN_DAYS: u64 = 14;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum Genre {
     Fiction,
     NonFiction,
     ScienceFiction,
     Mystery,
+    Other,
 }
 
 impl fmt::Display for Genre {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Genre::Fiction => write!(f, "Fiction"),
-            Genre::NonFiction => write!(f, "Non-Fiction"),
-            Genre::ScienceFiction => write!(f, "Science Fiction"),
-            Genre::Mystery => write!(f, "Mystery"),
+            Genre::Fiction => f.write_str(t("Fiction")),
+            Genre::NonFiction => f.write_str(t("Non-Fiction")),
+            Genre::ScienceFiction => f.write_str(t("Science Fiction")),
+            Genre::Mystery => f.write_str(t("Mystery")),
+            Genre::Other => f.write_str(t("Other")),
+        }
+    }
+}
+
+impl Genre {
+    pub const ALL: [Genre; 5] = [
+        Genre::Fiction,
+        Genre::NonFiction,
+        Genre::ScienceFiction,
+        Genre::Mystery,
+        Genre::Other,
+    ];
+
+    /// Best-effort mapping from a free-form tag (e.g. a Calibre tag) to a genre.
+    pub fn from_tag(tag: &str) -> Option<Genre> {
+        match tag.trim().to_lowercase().as_str() {
+            "fiction" | "novel" | "literary fiction" => Some(Genre::Fiction),
+            "non-fiction" | "nonfiction" | "non fiction" => Some(Genre::NonFiction),
+            "science fiction" | "sci-fi" | "scifi" | "sf" => Some(Genre::ScienceFiction),
+            "mystery" | "crime" | "detective" | "thriller" => Some(Genre::Mystery),
+            _ => None,
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-enum Status {
+pub enum Status {
     Available,
     CheckedOut,
     Lost,
@@ -34,62 +63,196 @@ enum Status {
 impl fmt::Display for Status {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Status::Available => write!(f, "Available"),
-            Status::CheckedOut => write!(f, "Checked Out"),
-            Status::Lost => write!(f, "Lost"),
+            Status::Available => f.write_str(t("Available")),
+            Status::CheckedOut => f.write_str(t("Checked Out")),
+            Status::Lost => f.write_str(t("Lost")),
         }
     }
 }
 
+impl Status {
+    pub const ALL: [Status; 3] = [Status::Available, Status::CheckedOut, Status::Lost];
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Series {
+    pub name: String,
+    pub index: f64,
+}
+
+impl fmt::Display for Series {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} #{}", self.name, self.index)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Book {
     title: String,
     author: String,
-    isbn: String,
+    isbn: Isbn,
     publication_year: u16,
     genre: Genre,
     status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    series: Option<Series>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
 }
 
 impl Book {
-    fn new(title: &str, author: &str, isbn: &str, publication_year: u16, genre: Genre) -> Book {
+    pub(crate) fn new(
+        title: &str,
+        author: &str,
+        isbn: Isbn,
+        publication_year: u16,
+        genre: Genre,
+    ) -> Book {
         Book {
             title: String::from(title),
             author: String::from(author),
-            isbn: String::from(isbn),
+            isbn,
             publication_year,
             genre,
             status: Status::Available,
+            series: None,
+            tags: Vec::new(),
         }
     }
 
+    pub(crate) fn with_series(mut self, series: Option<Series>) -> Book {
+        self.series = series;
+        self
+    }
+
+    pub(crate) fn with_tags(mut self, tags: Vec<String>) -> Book {
+        self.tags = tags;
+        self
+    }
+
+    #[allow(clippy::needless_return)]
     pub fn get_title(&self) -> &str {
         return &self.title;
     }
 
+    #[allow(clippy::needless_return)]
     pub fn get_author(&self) -> &str {
         return &self.author;
     }
 
-    pub fn get_isbn(&self) -> &str {
+    #[allow(clippy::needless_return)]
+    pub fn get_isbn(&self) -> &Isbn {
         return &self.isbn;
     }
 
+    pub fn get_publication_year(&self) -> u16 {
+        self.publication_year
+    }
+
+    pub fn get_genre(&self) -> &Genre {
+        &self.genre
+    }
+
+    pub fn get_status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn get_series(&self) -> Option<&Series> {
+        self.series.as_ref()
+    }
+
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    #[allow(clippy::needless_return)]
     pub fn get_available(&self) -> bool {
         return self.status == Status::Available;
     }
 
-    fn check_out(&mut self) -> Result<(), &str> {
+    /// Fills in fields this book is missing from `other`, which describes the
+    /// same ISBN. Returns whether anything changed.
+    pub(crate) fn merge_from(&mut self, other: &Book) -> bool {
+        let mut changed = false;
+        if self.publication_year == 0 && other.publication_year != 0 {
+            self.publication_year = other.publication_year;
+            changed = true;
+        }
+        if self.genre == Genre::Other && other.genre != Genre::Other {
+            self.genre = other.genre.clone();
+            changed = true;
+        }
+        if self.series.is_none() && other.series.is_some() {
+            self.series = other.series.clone();
+            changed = true;
+        }
+        for tag in other.tags.iter() {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    #[allow(clippy::needless_return)]
+    fn check_out(&mut self) -> Result<(), &'static str> {
         match self.status {
             Status::Available => {}
-            _ => return Err("Book is not available!"),
+            _ => return Err(t("Book is not available!")),
         }
         self.status = Status::CheckedOut;
         return Ok(());
     }
+
+    fn check_in(&mut self) -> Result<(), &'static str> {
+        match self.status {
+            Status::CheckedOut => {}
+            _ => return Err(t("Book is not checked out!")),
+        }
+        self.status = Status::Available;
+        Ok(())
+    }
+}
+
+/// One checkout of a book; kept after the return as loan history.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Loan {
+    pub isbn: Isbn,
+    pub patron: String,
+    pub checked_out: NaiveDate,
+    pub due: NaiveDate,
+    pub returned: Option<NaiveDate>,
+}
+
+impl Loan {
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.returned.is_none() && today > self.due
+    }
 }
 
+/// A patron waiting for a book that is currently not available.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hold {
+    pub isbn: Isbn,
+    pub patron: String,
+    pub placed: NaiveDate,
+}
+
+/// Someone who has borrowed or reserved a book. Patrons aren't stored on
+/// their own; they're gathered from loans and holds.
 #[derive(Debug)]
+pub struct Patron<'a> {
+    pub name: &'a str,
+    /// Books they have out now, soonest due first.
+    pub on_loan: Vec<&'a Loan>,
+    pub holds: Vec<&'a Hold>,
+    /// Every loan they've had, including the current ones.
+    pub loans: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum LibrarySearchCriteria {
     Author,
     Title,
@@ -99,15 +262,16 @@ pub enum LibrarySearchCriteria {
 impl fmt::Display for LibrarySearchCriteria {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            LibrarySearchCriteria::Author => write!(f, "Author"),
-            LibrarySearchCriteria::Title => write!(f, "Title"),
-            LibrarySearchCriteria::ISBN => write!(f, "ISBN"),
+            LibrarySearchCriteria::Author => f.write_str(t("Author")),
+            LibrarySearchCriteria::Title => f.write_str(t("Title")),
+            LibrarySearchCriteria::ISBN => f.write_str(t("ISBN")),
         }
     }
 }
 
 impl LibrarySearchCriteria {
-    fn matches<'a, T>(&self, book: &'a Book, value: T) -> bool
+    #[allow(clippy::needless_lifetimes)]
+    pub fn matches<'a, T>(&self, book: &'a Book, value: T) -> bool
     where
         T: AsRef<str>,
     {
@@ -120,104 +284,341 @@ impl LibrarySearchCriteria {
                 .title
                 .to_lowercase()
                 .contains(&value.as_ref().to_lowercase()),
-            LibrarySearchCriteria::ISBN => book.isbn.eq(&value.as_ref()),
+            // A complete ISBN matches in either form; a partial one matches
+            // as a run of digits so results narrow while typing.
+            LibrarySearchCriteria::ISBN => match Isbn::parse(value.as_ref()) {
+                Ok(isbn) => book.isbn == isbn,
+                Err(_) => {
+                    let digits: String = value
+                        .as_ref()
+                        .chars()
+                        .filter(|c| c.is_ascii_alphanumeric())
+                        .collect();
+                    !digits.is_empty() && book.isbn.as_str().contains(&digits)
+                }
+            },
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Library {
     books: Vec<Book>,
     owner: String,
+    #[serde(default)]
+    loans: Vec<Loan>,
+    /// Holds in queue order, oldest first.
+    #[serde(default)]
+    holds: Vec<Hold>,
 }
 
 impl Library {
-    fn new(owner: &str) -> Library {
+    pub(crate) fn new(owner: &str) -> Library {
         Library {
             books: Vec::new(),
             owner: String::from(owner),
+            loans: Vec::new(),
+            holds: Vec::new(),
         }
     }
 
+    #[allow(clippy::needless_return)]
     pub fn get_owner(&self) -> &str {
         return &self.owner;
     }
 
-    fn add(&mut self, book: Book) {
+    pub(crate) fn add(&mut self, book: Book) {
         self.books.push(book);
     }
 
-    pub fn search_by<T>(&self, criteria: &LibrarySearchCriteria, value: T) -> Option<&Book>
-    where
-        T: AsRef<str>,
-    {
-        return self.books.iter().find(|x| criteria.matches(x, &value));
+    pub fn find(&self, isbn: &Isbn) -> Option<&Book> {
+        self.books.iter().find(|x| x.isbn.eq(isbn))
     }
 
+    pub(crate) fn find_mut(&mut self, isbn: &Isbn) -> Option<&mut Book> {
+        self.books.iter_mut().find(|x| x.isbn.eq(isbn))
+    }
+
+    /// Writes the library to `path`. The file is replaced in one step, so
+    /// readers such as the OPDS server never see it half written.
     pub fn save(&self, path: &str) -> Result<(), io::Error> {
         let json = serde_json::to_string(&self).unwrap();
-        return std::fs::write(path, json);
+        let partial = format!("{}.partial", path);
+        std::fs::write(&partial, json)?;
+        let renamed = std::fs::rename(&partial, path);
+        if renamed.is_err() {
+            let _ = std::fs::remove_file(&partial);
+        }
+        renamed
     }
 
-    pub fn from_file(pathname: &str) -> Option<Library> {
-        let path = Path::new(pathname);
-        if path.exists() && path.is_file() {
-            let s = read_to_string(path).unwrap();
-            let library: Library = serde_json::from_str(&s).unwrap();
-            return Some(library);
-        } else {
-            File::create(pathname).unwrap();
-            return None;
+    /// Reads the library at `pathname`; `None` when there is no file yet.
+    pub fn from_file(pathname: &str) -> Result<Option<Library>, io::Error> {
+        Library::from_file_with_progress(pathname, &mut |_, _| {})
+    }
+
+    /// Like `from_file`, calling `progress` with the bytes read so far and
+    /// the size of the file.
+    pub fn from_file_with_progress(
+        pathname: &str,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Option<Library>, io::Error> {
+        let mut file = match File::open(pathname) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let total = file.metadata()?.len() as usize;
+        let mut bytes = Vec::with_capacity(total);
+        let mut chunk = [0; 64 * 1024];
+        loop {
+            let read = file.read(&mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            bytes.extend_from_slice(&chunk[..read]);
+            progress(bytes.len(), total);
+        }
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+
+    pub fn check_out(
+        &mut self,
+        isbn: &Isbn,
+        patron: &str,
+        today: NaiveDate,
+    ) -> Result<(), &'static str> {
+        match self.books.iter_mut().find(|x| x.isbn.eq(isbn)) {
+            Some(book) => book.check_out()?,
+            None => return Err(t("Book not found!")),
         }
+        self.loans.push(Loan {
+            isbn: isbn.clone(),
+            patron: String::from(patron),
+            checked_out: today,
+            due: today + Days::new(LOAN_DAYS),
+            returned: None,
+        });
+        // Borrowing a book fulfills the patron's own hold on it.
+        self.holds
+            .retain(|hold| !(hold.isbn.eq(isbn) && hold.patron == patron));
+        Ok(())
     }
 
-    pub fn check_out(&mut self, isbn: &str) -> Result<(), &str> {
+    pub fn return_book(&mut self, isbn: &Isbn, today: NaiveDate) -> Result<(), &'static str> {
         match self.books.iter_mut().find(|x| x.isbn.eq(isbn)) {
-            Some(book) => book.check_out(),
-            None => Err("Book not found!"),
+            Some(book) => book.check_in()?,
+            None => return Err(t("Book not found!")),
+        }
+        for loan in self.loans.iter_mut() {
+            if loan.isbn.eq(isbn) && loan.returned.is_none() {
+                loan.returned = Some(today);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn place_hold(
+        &mut self,
+        isbn: &Isbn,
+        patron: &str,
+        today: NaiveDate,
+    ) -> Result<(), &'static str> {
+        match self.books.iter().find(|x| x.isbn.eq(isbn)) {
+            Some(book) if book.get_available() => {
+                return Err(t("Book is available, check it out!"))
+            }
+            Some(_) => {}
+            None => return Err(t("Book not found!")),
+        }
+        if self
+            .current_loan(isbn)
+            .is_some_and(|loan| loan.patron == patron)
+        {
+            return Err(t("Patron already has this book!"));
+        }
+        if self
+            .holds_for(isbn)
+            .iter()
+            .any(|hold| hold.patron == patron)
+        {
+            return Err(t("Patron is already waiting for this book!"));
         }
+        self.holds.push(Hold {
+            isbn: isbn.clone(),
+            patron: String::from(patron),
+            placed: today,
+        });
+        Ok(())
     }
 
+    pub fn current_loan(&self, isbn: &Isbn) -> Option<&Loan> {
+        self.loans
+            .iter()
+            .find(|loan| loan.isbn.eq(isbn) && loan.returned.is_none())
+    }
+
+    /// Holds on a book in queue order.
+    pub fn holds_for(&self, isbn: &Isbn) -> Vec<&Hold> {
+        self.holds
+            .iter()
+            .filter(|hold| hold.isbn.eq(isbn))
+            .collect()
+    }
+
+    /// Books out on loan now, soonest due first.
+    pub fn active_loans(&self) -> Vec<&Loan> {
+        let mut loans: Vec<&Loan> = self
+            .loans
+            .iter()
+            .filter(|loan| loan.returned.is_none())
+            .collect();
+        loans.sort_by_key(|loan| loan.due);
+        loans
+    }
+
+    /// Everyone with a loan or hold, past or present, by name.
+    pub fn patrons(&self) -> Vec<Patron<'_>> {
+        let mut names: Vec<&str> = self
+            .loans
+            .iter()
+            .map(|loan| loan.patron.as_str())
+            .chain(self.holds.iter().map(|hold| hold.patron.as_str()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| Patron {
+                name,
+                on_loan: self
+                    .active_loans()
+                    .into_iter()
+                    .filter(|loan| loan.patron == name)
+                    .collect(),
+                holds: self
+                    .holds
+                    .iter()
+                    .filter(|hold| hold.patron == name)
+                    .collect(),
+                loans: self.loans.iter().filter(|loan| loan.patron == name).count(),
+            })
+            .collect()
+    }
+
+    /// Every loan of a book, oldest first, including the current one.
+    pub fn loan_history(&self, isbn: &Isbn) -> Vec<&Loan> {
+        self.loans
+            .iter()
+            .filter(|loan| loan.isbn.eq(isbn))
+            .collect()
+    }
+
+    #[allow(clippy::needless_return)]
     pub fn initialize_demo(owner: &str) -> Library {
         let mut library: Library = Library::new(owner);
         library.add(Book::new(
             "The Great Gatsby",
             "F. Scott Fitzgerald",
-            "9780743273565",
+            Isbn::parse("9780743273565").unwrap(),
             1925,
             Genre::Fiction,
         ));
         library.add(Book::new(
             "To Kill a Mockingbird",
             "Harper Lee",
-            "9780061120084",
+            Isbn::parse("9780061120084").unwrap(),
             1960,
             Genre::Fiction,
         ));
         library.add(Book::new(
             "1984",
             "George Orwell",
-            "9780451524935",
+            Isbn::parse("9780451524935").unwrap(),
             1949,
             Genre::ScienceFiction,
         ));
         return library;
     }
 
+    #[allow(clippy::needless_return)]
     pub fn get_books(&self) -> &Vec<Book> {
         return &self.books;
     }
-}
 
-#[derive(Debug)]
-pub struct Config {
-    pub library_path: String,
+    pub fn get_loans(&self) -> &[Loan] {
+        &self.loans
+    }
 }
 
-impl Config {
-    pub fn new(library_path: &str) -> Config {
-        Config {
-            library_path: String::from(library_path),
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn temp_file(name: &str, contents: Option<&str>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("libros-{}-{}", std::process::id(), name));
+        match contents {
+            Some(contents) => fs::write(&path, contents).unwrap(),
+            None => {
+                let _ = fs::remove_file(&path);
+            }
         }
+        path
+    }
+
+    #[test]
+    fn missing_file_is_no_library() {
+        let path = temp_file("missing.json", None);
+        assert!(Library::from_file(path.to_str().unwrap())
+            .unwrap()
+            .is_none());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn malformed_file_is_an_error() {
+        let path = temp_file("malformed.json", Some("{\"books\": ["));
+        assert!(Library::from_file(path.to_str().unwrap()).is_err());
+        let path = temp_file("empty.json", Some(""));
+        assert!(Library::from_file(path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn invalid_isbns_still_load() {
+        let json = r#"{"owner": "Ana", "books": [{"title": "Old", "author": "Someone",
+            "isbn": "n/a", "publication_year": 1900, "genre": "Other", "status": "Available"}]}"#;
+        let path = temp_file("invalid-isbn.json", Some(json));
+        let library = Library::from_file(path.to_str().unwrap()).unwrap().unwrap();
+        assert_eq!(library.get_books()[0].get_isbn().as_str(), "n/a");
+    }
+
+    #[test]
+    fn loans_are_overdue_after_the_due_date() {
+        let library: Library = serde_json::from_str(include_str!("testdata/library.json")).unwrap();
+        let overdue = |day| {
+            let today = NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+            let loans = library.active_loans();
+            let patrons = library.patrons();
+            let by_patron: Vec<usize> = patrons
+                .iter()
+                .map(|p| {
+                    p.on_loan
+                        .iter()
+                        .filter(|loan| loan.is_overdue(today))
+                        .count()
+                })
+                .collect();
+            (
+                loans.iter().filter(|loan| loan.is_overdue(today)).count(),
+                by_patron,
+            )
+        };
+        // Ana, Bo and Cy; Bo returned their book on time.
+        assert_eq!(overdue(5), (0, vec![0, 0, 0]));
+        assert_eq!(overdue(6), (1, vec![0, 0, 1]));
+        assert_eq!(overdue(19), (2, vec![1, 0, 1]));
     }
 }
@@ -1,164 +1,190 @@
 mod app;
+mod calibre;
+mod cli;
+mod commands;
+mod config;
+mod html;
+mod i18n;
+mod input;
+mod isbn;
+mod keymap;
 mod library;
+mod metadata;
+mod notifications;
+mod opds;
+mod stats;
+mod tasks;
+mod terminal;
+mod theme;
 mod ui;
+#[cfg(test)]
+mod ui_tests;
 
-use app::{App, CurrentScreen};
-use crossterm::event::{self, DisableMouseCapture, Event, KeyCode};
-use crossterm::terminal::{disable_raw_mode, LeaveAlternateScreen};
-use library::LibrarySearchCriteria;
-use ratatui::crossterm::event::EnableMouseCapture;
-use ratatui::crossterm::execute;
-use ratatui::crossterm::terminal::{enable_raw_mode, EnterAlternateScreen};
+use app::{App, Effect};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::prelude::{Backend, CrosstermBackend};
 use ratatui::Terminal;
 use std::error::Error;
 use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use terminal::Signals;
 use ui::ui;
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<bool, io::Error> {
+/// How often the app ticks: redraws the clock and spinner, and checks on
+/// toasts, autosave and background tasks.
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// How the app stopped.
+enum Exit {
+    /// Quit from the app; `true` saves the library first.
+    Quit(bool),
+    /// Stopped by a signal such as SIGTERM, or by Ctrl-C.
+    Terminated,
+}
+
+/// Ctrl-Z, which raw mode delivers as a key instead of SIGTSTP.
+fn is_suspend(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// Ctrl-C, which raw mode delivers as a key instead of SIGINT.
+fn is_interrupt(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+/// Carries out the effects of an update, returning how the app stops if
+/// one of them quits.
+fn apply(app: &mut App, effects: Vec<Effect>, render: &mut bool) -> Option<Exit> {
+    for effect in effects {
+        match effect {
+            Effect::Render => *render = true,
+            Effect::SaveConfig => app.save_config(),
+            Effect::SaveLibrary => app.save_library(),
+            Effect::Spawn(task) => app.start_task(task),
+            Effect::Quit(save) => return Some(Exit::Quit(save)),
+        }
+    }
+    None
+}
+
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    signals: &Signals,
+) -> Result<Exit, io::Error> {
+    let mut last_tick = Instant::now();
+    let mut render = true;
     loop {
-        terminal.draw(|f| ui(f, app))?;
+        if signals.terminated() {
+            return Ok(Exit::Terminated);
+        }
+        if signals.take_suspend() {
+            terminal::suspend()?;
+            terminal.clear()?;
+            render = true;
+        }
+        if render {
+            terminal.draw(|f| ui(f, app))?;
+            render = false;
+        }
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == event::KeyEventKind::Release {
-                continue;
+        // Wake up for the next tick, or sooner for a debounced search.
+        let mut timeout = TICK_RATE.saturating_sub(last_tick.elapsed());
+        if let Some(search) = app.search_timeout() {
+            timeout = timeout.min(search);
+        }
+        if event::poll(timeout)? {
+            let effects = match event::read()? {
+                Event::Key(key) if is_suspend(&key) => {
+                    terminal::suspend()?;
+                    terminal.clear()?;
+                    vec![Effect::Render]
+                }
+                Event::Key(key) if is_interrupt(&key) => return Ok(Exit::Terminated),
+                Event::Key(key) => app.handle_key(key),
+                Event::Mouse(mouse) => app.handle_mouse(mouse),
+                Event::Paste(text) => app.paste(&text),
+                Event::Resize(..) => vec![Effect::Render],
+                _ => Vec::new(),
+            };
+            if let Some(exit) = apply(app, effects, &mut render) {
+                return Ok(exit);
             }
-            match app.current_screen {
-                CurrentScreen::Loading => match key.code {
-                    KeyCode::Esc => {
-                        return Ok(false);
-                    }
-                    KeyCode::Enter => {
-                        app.load();
-                        if app.loaded {
-                            app.current_screen = CurrentScreen::Home;
-                        } else {
-                            app.current_screen = CurrentScreen::NewOwner;
-                        }
-                    }
-                    _ => {}
-                },
-                CurrentScreen::NewOwner => match key.code {
-                    KeyCode::Char(value) => {
-                        app.owner_input.push(value);
-                    }
-                    KeyCode::Enter => {
-                        app.initialize_demo();
-                        app.current_screen = CurrentScreen::Home;
-                    }
-                    KeyCode::Backspace => {
-                        app.owner_input.pop();
-                    }
-                    _ => {}
-                },
-                CurrentScreen::Home => match key.code {
-                    KeyCode::Char('q') => {
-                        app.current_screen = CurrentScreen::Exiting;
-                    }
-                    KeyCode::Char('s') => {
-                        app.current_screen = CurrentScreen::Searching;
-                        app.term_input_mode = true;
-                        app.searching_input.clear();
-                    }
-                    _ => {}
-                },
-                CurrentScreen::Searching => match key.code {
-                    KeyCode::Tab => {
-                        app.term_input_mode = !app.term_input_mode;
-                    }
-                    KeyCode::Char(value) => {
-                        if app.term_input_mode {
-                            app.searching_input.push(value);
-                        } else {
-                            match value {
-                                't' => app.searching_criteria = LibrarySearchCriteria::Title,
-                                'a' => app.searching_criteria = LibrarySearchCriteria::Author,
-                                'i' => app.searching_criteria = LibrarySearchCriteria::ISBN,
-                                'q' => app.current_screen = CurrentScreen::Exiting,
-                                _ => {}
-                            }
-                        }
-                    }
-                    KeyCode::Backspace => {
-                        app.searching_input.pop();
-                    }
-                    KeyCode::Enter => {
-                        app.apply_search();
-                        if app.selected_book.is_some() {
-                            app.current_screen = CurrentScreen::CheckingOut;
-                        }
-                    }
-                    KeyCode::Esc => {
-                        app.current_screen = CurrentScreen::Home;
-                    }
-                    _ => {}
-                },
-                CurrentScreen::CheckingOut => match key.code {
-                    KeyCode::Enter => {
-                        app.check_out();
-                        if app.checkout_success.is_none() {
-                            app.error_message = Some("Book not found".to_string());
-                        }
-                        app.current_screen = CurrentScreen::CheckedOutResult;
-                    }
-                    KeyCode::Char('q') => {
-                        app.current_screen = CurrentScreen::Exiting;
-                    }
-                    KeyCode::Char('b') => {
-                        app.current_screen = CurrentScreen::Searching;
-                    }
-                    KeyCode::Esc => {
-                        app.current_screen = CurrentScreen::Home;
-                    }
-                    _ => {}
-                },
-                CurrentScreen::CheckedOutResult => match key.code {
-                    KeyCode::Enter => {
-                        app.current_screen = CurrentScreen::Home;
-                    }
-                    _ => {}
-                },
-                CurrentScreen::Exiting => match key.code {
-                    KeyCode::Char('y') => {
-                        return Ok(true);
-                    }
-                    KeyCode::Char('n') => {
-                        app.current_screen = CurrentScreen::Home;
-                    }
-                    _ => {}
-                },
+        }
+
+        let search_due = app.search_timeout().is_some_and(|t| t.is_zero());
+        if last_tick.elapsed() >= TICK_RATE || search_due {
+            let effects = app.tick();
+            last_tick = Instant::now();
+            if let Some(exit) = apply(app, effects, &mut render) {
+                return Ok(exit);
             }
         }
     }
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    enable_raw_mode()?;
-    let mut stderr = io::stderr(); // This is a special case. Normally using stdout is fine
-    execute!(stderr, EnterAlternateScreen, EnableMouseCapture)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&args) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    let backend = CrosstermBackend::new(stderr);
+    let signals = Signals::register()?;
+    terminal::install_panic_hook();
+    terminal::setup()?;
+    // This is a special case. Normally using stdout is fine
+    let backend = CrosstermBackend::new(io::stderr());
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
     let mut app = App::new();
-    let res = run_app(&mut terminal, &mut app);
-
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_app(&mut terminal, &mut app, &signals)
+    }));
+    // Carry on when the terminal can't be restored, so unsaved changes
+    // still reach the recovery file.
+    if let Err(err) = terminal::restore() {
+        eprintln!("Couldn't restore the terminal: {err}");
+    }
 
-    if let Ok(save) = res {
-        if save {
-            app.library.unwrap().save(&app.config.library_path)?;
+    match res {
+        Ok(Ok(Exit::Quit(true))) => {
+            if let Err(err) = app.write_library() {
+                eprintln!("Couldn't save library: {err}");
+                save_recovery(&app);
+                std::process::exit(1);
+            }
+        }
+        Ok(Ok(Exit::Quit(false))) => {}
+        Ok(Ok(Exit::Terminated)) => {
+            save_recovery(&app);
+            std::process::exit(1);
+        }
+        Ok(Err(err)) => {
+            println!("{err:?}");
+            save_recovery(&app);
+        }
+        // The panic hook already printed the message.
+        Err(_) => {
+            save_recovery(&app);
+            std::process::exit(101);
         }
-    } else if let Err(err) = res {
-        println!("{err:?}");
     }
 
     Ok(())
 }
+
+/// Writes unsaved changes to the recovery file after an abnormal exit or a
+/// failed save.
+fn save_recovery(app: &App) {
+    match app.save_recovery() {
+        Some(Ok(path)) => eprintln!("Unsaved changes were written to {path}"),
+        Some(Err(err)) => eprintln!("Couldn't write unsaved changes: {err}"),
+        None => {}
+    }
+}
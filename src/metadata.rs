@@ -0,0 +1,199 @@
+use serde_json::{json, Map, Value};
+
+use crate::html::escape;
+use crate::library::{Book, Library};
+
+/// Co-authors are stored joined with " & " (the Calibre convention).
+fn authors(book: &Book) -> impl Iterator<Item = &str> {
+    book.get_author()
+        .split(" & ")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Describes `book` as a schema.org `Book` node.
+pub fn json_ld(book: &Book) -> Value {
+    let mut node = Map::new();
+    node.insert("@type".into(), json!("Book"));
+    if book.get_isbn().is_valid() {
+        node.insert("@id".into(), json!(format!("urn:isbn:{}", book.get_isbn())));
+    }
+    node.insert("name".into(), json!(book.get_title()));
+    node.insert(
+        "author".into(),
+        authors(book)
+            .map(|name| json!({ "@type": "Person", "name": name }))
+            .collect(),
+    );
+    node.insert("isbn".into(), json!(book.get_isbn().as_str()));
+    if book.get_publication_year() != 0 {
+        node.insert(
+            "datePublished".into(),
+            json!(book.get_publication_year().to_string()),
+        );
+    }
+    node.insert("genre".into(), json!(book.get_genre().to_string()));
+    if let Some(series) = book.get_series() {
+        node.insert(
+            "isPartOf".into(),
+            json!({ "@type": "BookSeries", "name": series.name }),
+        );
+        node.insert("position".into(), json!(series.index));
+    }
+    if !book.get_tags().is_empty() {
+        node.insert("keywords".into(), json!(book.get_tags().join(", ")));
+    }
+    Value::Object(node)
+}
+
+/// The whole catalog as a single JSON-LD document with one node per book.
+pub fn catalog_json_ld(library: &Library) -> Value {
+    json!({
+        "@context": "https://schema.org",
+        "@graph": library.get_books().iter().map(json_ld).collect::<Vec<Value>>(),
+    })
+}
+
+/// Describes `book` as an unqualified Dublin Core record (OAI-DC).
+pub fn dublin_core(book: &Book) -> String {
+    let mut elements = vec![("title", book.get_title().to_string())];
+    for name in authors(book) {
+        elements.push(("creator", name.to_string()));
+    }
+    if book.get_isbn().is_valid() {
+        elements.push(("identifier", format!("urn:isbn:{}", book.get_isbn())));
+    }
+    if book.get_publication_year() != 0 {
+        elements.push(("date", book.get_publication_year().to_string()));
+    }
+    let genre = book.get_genre().to_string();
+    for tag in book.get_tags().iter() {
+        if !tag.eq_ignore_ascii_case(&genre) {
+            elements.push(("subject", tag.clone()));
+        }
+    }
+    elements.push(("subject", genre));
+    if let Some(series) = book.get_series() {
+        elements.push(("relation", series.to_string()));
+    }
+    elements.push(("type", "Text".to_string()));
+
+    let mut xml = String::from(
+        "<oai_dc:dc xmlns:oai_dc=\"http://www.openarchives.org/OAI/2.0/oai_dc/\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+         xsi:schemaLocation=\"http://www.openarchives.org/OAI/2.0/oai_dc/ \
+         http://www.openarchives.org/OAI/2.0/oai_dc.xsd\">\n",
+    );
+    for (element, value) in elements.iter() {
+        xml.push_str(&format!(
+            "  <dc:{}>{}</dc:{}>\n",
+            element,
+            escape(value),
+            element
+        ));
+    }
+    xml.push_str("</oai_dc:dc>\n");
+    xml
+}
+
+/// The whole catalog as OAI-DC records wrapped in a `<records>` element.
+pub fn catalog_dublin_core(library: &Library) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<records>\n");
+    for book in library.get_books().iter() {
+        xml.push_str(&dublin_core(book));
+    }
+    xml.push_str("</records>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isbn::Isbn;
+    use crate::library::{Genre, Series};
+
+    fn dune() -> Book {
+        Book::new(
+            "Dune",
+            "Frank Herbert & Brian Herbert",
+            Isbn::parse("9780441172719").unwrap(),
+            1965,
+            Genre::ScienceFiction,
+        )
+        .with_series(Some(Series {
+            name: "Dune".to_string(),
+            index: 1.0,
+        }))
+        .with_tags(vec!["Classics".to_string(), "science fiction".to_string()])
+    }
+
+    fn raw() -> Book {
+        Book::new(
+            "Tom & Jerry <Annual>",
+            "\"Hanna\"",
+            Isbn::from("n/a".to_string()),
+            0,
+            Genre::Other,
+        )
+    }
+
+    #[test]
+    fn json_ld_describes_the_book() {
+        assert_eq!(
+            json_ld(&dune()),
+            json!({
+                "@type": "Book",
+                "@id": "urn:isbn:9780441172719",
+                "name": "Dune",
+                "author": [
+                    { "@type": "Person", "name": "Frank Herbert" },
+                    { "@type": "Person", "name": "Brian Herbert" },
+                ],
+                "isbn": "9780441172719",
+                "datePublished": "1965",
+                "genre": "Science Fiction",
+                "isPartOf": { "@type": "BookSeries", "name": "Dune" },
+                "position": 1.0,
+                "keywords": "Classics, science fiction",
+            })
+        );
+    }
+
+    #[test]
+    fn json_ld_has_no_urn_for_raw_isbns() {
+        let node = json_ld(&raw());
+        assert!(node.get("@id").is_none());
+        assert!(node.get("datePublished").is_none());
+    }
+
+    #[test]
+    fn dublin_core_describes_the_book() {
+        let xml = dublin_core(&dune());
+        let elements: Vec<&str> = xml.lines().skip(1).map(str::trim).collect();
+        assert_eq!(
+            elements,
+            [
+                "<dc:title>Dune</dc:title>",
+                "<dc:creator>Frank Herbert</dc:creator>",
+                "<dc:creator>Brian Herbert</dc:creator>",
+                "<dc:identifier>urn:isbn:9780441172719</dc:identifier>",
+                "<dc:date>1965</dc:date>",
+                "<dc:subject>Classics</dc:subject>",
+                "<dc:subject>Science Fiction</dc:subject>",
+                "<dc:relation>Dune #1</dc:relation>",
+                "<dc:type>Text</dc:type>",
+                "</oai_dc:dc>",
+            ]
+        );
+    }
+
+    #[test]
+    fn dublin_core_escapes_and_skips_raw_isbns() {
+        let xml = dublin_core(&raw());
+        assert!(xml.contains("<dc:title>Tom &amp; Jerry &lt;Annual&gt;</dc:title>"));
+        assert!(xml.contains("<dc:creator>&quot;Hanna&quot;</dc:creator>"));
+        assert!(!xml.contains("dc:identifier"));
+        assert!(!xml.contains("dc:date"));
+    }
+}
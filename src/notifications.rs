@@ -0,0 +1,91 @@
+use chrono::{DateTime, Local, TimeDelta};
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::i18n::t;
+
+/// How many toasts are on screen at once; the rest wait for room.
+const VISIBLE: usize = 3;
+/// How many messages the history keeps.
+const HISTORY: usize = 200;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    /// How long a toast stays up; problems stay longer.
+    fn duration(self) -> TimeDelta {
+        match self {
+            Level::Info => TimeDelta::seconds(3),
+            Level::Warning => TimeDelta::seconds(5),
+            Level::Error => TimeDelta::seconds(8),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Info => f.write_str(t("Info")),
+            Level::Warning => f.write_str(t("Warning")),
+            Level::Error => f.write_str(t("Error")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub level: Level,
+    pub text: String,
+    pub time: DateTime<Local>,
+}
+
+/// Messages about what just happened: shown as toasts for a while, and
+/// kept in a history.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    /// Toasts on screen, oldest first, with when each one goes away.
+    shown: Vec<(Notification, DateTime<Local>)>,
+    /// Toasts waiting for room on screen.
+    queued: VecDeque<Notification>,
+    /// Every message, oldest first.
+    history: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, level: Level, text: String, time: DateTime<Local>) {
+        let notification = Notification { level, text, time };
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(notification.clone());
+        self.queued.push_back(notification);
+        self.expire(time);
+    }
+
+    /// Takes down the toasts whose time is up and shows waiting ones in
+    /// their place.
+    pub fn expire(&mut self, now: DateTime<Local>) {
+        self.shown.retain(|(_, until)| *until > now);
+        while self.shown.len() < VISIBLE {
+            let Some(notification) = self.queued.pop_front() else {
+                break;
+            };
+            let until = now + notification.level.duration();
+            self.shown.push((notification, until));
+        }
+    }
+
+    /// The toasts to draw, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.shown.iter().map(|(notification, _)| notification)
+    }
+
+    pub fn history(&self) -> &VecDeque<Notification> {
+        &self.history
+    }
+}
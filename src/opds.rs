@@ -0,0 +1,440 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{SecondsFormat, Utc};
+
+use crate::html::{escape, slug, Slugs};
+use crate::i18n::tr;
+use crate::isbn::Isbn;
+use crate::library::{Book, Library};
+
+const NAVIGATION: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+const ACQUISITION: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+const ENTRY: &str = "application/atom+xml;type=entry;profile=opds-catalog";
+
+/// How many books the "Newest" feed lists.
+const NEWEST_LIMIT: usize = 25;
+
+/// How long the server waits for a request line.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A link to another feed, listed in a navigation feed.
+struct NavEntry {
+    title: String,
+    href: String,
+    content: String,
+    kind: &'static str,
+}
+
+struct Feeds<'a> {
+    library: &'a Library,
+    updated: String,
+    files: BTreeMap<String, String>,
+    /// The entry document of each book.
+    entries: HashMap<Isbn, String>,
+}
+
+impl Feeds<'_> {
+    fn entry_file(&self, book: &Book) -> &str {
+        &self.entries[book.get_isbn()]
+    }
+
+    fn id(&self, name: &str) -> String {
+        format!("urn:libros:{}:{}", slug(self.library.get_owner()), name)
+    }
+
+    fn header(&self, file: &str, title: &str, kind: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\" \
+             xmlns:dc=\"http://purl.org/dc/terms/\" \
+             xmlns:opds=\"http://opds-spec.org/2010/catalog\">\n\
+             <id>{}</id>\n<title>{}</title>\n<updated>{}</updated>\n\
+             <author><name>{}</name></author>\n\
+             <link rel=\"self\" href=\"{}\" type=\"{}\"/>\n\
+             <link rel=\"start\" href=\"index.xml\" type=\"{}\"/>\n",
+            self.id(file),
+            escape(title),
+            self.updated,
+            escape(self.library.get_owner()),
+            file,
+            kind,
+            NAVIGATION,
+        )
+    }
+
+    fn navigation(&mut self, file: &str, title: &str, entries: &[NavEntry]) {
+        let mut xml = self.header(file, title, NAVIGATION);
+        for entry in entries.iter() {
+            let _ = write!(
+                xml,
+                "<entry>\n<title>{}</title>\n<id>{}</id>\n<updated>{}</updated>\n\
+                 <content type=\"text\">{}</content>\n\
+                 <link rel=\"subsection\" href=\"{}\" type=\"{}\"/>\n</entry>\n",
+                escape(&entry.title),
+                self.id(&entry.href),
+                self.updated,
+                escape(&entry.content),
+                entry.href,
+                entry.kind,
+            );
+        }
+        xml.push_str("</feed>\n");
+        self.files.insert(file.to_string(), xml);
+    }
+
+    fn book_entry(&self, book: &Book) -> String {
+        let isbn = book.get_isbn();
+        // Only real ISBNs make an `urn:isbn:` identifier.
+        let id = match isbn.is_valid() {
+            true => format!("urn:isbn:{}", isbn.as_str()),
+            false => self.id(self.entry_file(book)),
+        };
+        let mut xml = format!(
+            "<title>{}</title>\n<id>{}</id>\n<updated>{}</updated>\n\
+             <author><name>{}</name></author>\n",
+            escape(book.get_title()),
+            escape(&id),
+            self.updated,
+            escape(book.get_author()),
+        );
+        if isbn.is_valid() {
+            let _ = writeln!(xml, "<dc:identifier>{}</dc:identifier>", id);
+        }
+        let _ = writeln!(
+            xml,
+            "<category term=\"{}\" label=\"{}\"/>",
+            escape(&slug(&book.get_genre().to_string())),
+            escape(&book.get_genre().to_string()),
+        );
+        if book.get_publication_year() != 0 {
+            let _ = writeln!(
+                xml,
+                "<dc:issued>{:04}</dc:issued>",
+                book.get_publication_year()
+            );
+        }
+        let _ = writeln!(
+            xml,
+            "<content type=\"text\">{}</content>",
+            escape(&book.get_status().to_string())
+        );
+        let _ = writeln!(
+            xml,
+            "<link rel=\"alternate\" href=\"{}\" type=\"{}\"/>",
+            self.entry_file(book),
+            ENTRY
+        );
+        xml
+    }
+
+    fn acquisition(&mut self, file: &str, title: &str, books: &[&Book]) {
+        let mut xml = self.header(file, title, ACQUISITION);
+        for book in books.iter() {
+            let _ = write!(xml, "<entry>\n{}</entry>\n", self.book_entry(book));
+        }
+        xml.push_str("</feed>\n");
+        self.files.insert(file.to_string(), xml);
+    }
+
+    fn entry_document(&mut self, book: &Book) {
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <entry xmlns=\"http://www.w3.org/2005/Atom\" \
+             xmlns:dc=\"http://purl.org/dc/terms/\" \
+             xmlns:opds=\"http://opds-spec.org/2010/catalog\">\n{}</entry>\n",
+            self.book_entry(book)
+        );
+        self.files.insert(self.entry_file(book).to_string(), xml);
+    }
+
+    /// Builds one navigation feed listing the groups, plus an acquisition
+    /// feed per group.
+    fn grouped(
+        &mut self,
+        file: &str,
+        title: &str,
+        prefix: &str,
+        groups: &BTreeMap<String, Vec<&Book>>,
+    ) {
+        let mut slugs = Slugs::default();
+        let mut entries = Vec::new();
+        for (name, books) in groups.iter() {
+            let href = format!("{}-{}.xml", prefix, slugs.unique(name, "unknown"));
+            self.acquisition(&href, name, books);
+            entries.push(NavEntry {
+                title: name.clone(),
+                href,
+                content: format!("{} book(s)", books.len()),
+                kind: ACQUISITION,
+            });
+        }
+        self.navigation(file, title, &entries);
+    }
+}
+
+/// Generates the OPDS 1.2 catalog for `library`, keyed by file name.
+///
+/// `index.xml` is the root navigation feed, linking to all books, the newest
+/// additions and per-author and per-genre acquisition feeds.
+pub fn catalog(library: &Library) -> BTreeMap<String, String> {
+    let mut feeds = Feeds {
+        library,
+        updated: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        files: BTreeMap::new(),
+        entries: HashMap::new(),
+    };
+
+    let mut books: Vec<&Book> = library.get_books().iter().collect();
+    // Books are appended as they are added, so the tail is the newest.
+    let newest: Vec<&Book> = books.iter().rev().take(NEWEST_LIMIT).copied().collect();
+    books.sort_by_key(|book| book.get_title().to_lowercase());
+    let mut slugs = Slugs::default();
+    for book in books.iter() {
+        feeds
+            .entries
+            .entry(book.get_isbn().clone())
+            .or_insert_with_key(|isbn| {
+                format!("entry-{}.xml", slugs.unique(isbn.as_str(), "book"))
+            });
+    }
+
+    let mut by_author: BTreeMap<String, Vec<&Book>> = BTreeMap::new();
+    let mut by_genre: BTreeMap<String, Vec<&Book>> = BTreeMap::new();
+    for book in books.iter() {
+        by_author
+            .entry(book.get_author().to_string())
+            .or_default()
+            .push(book);
+        by_genre
+            .entry(book.get_genre().to_string())
+            .or_default()
+            .push(book);
+        feeds.entry_document(book);
+    }
+
+    feeds.acquisition("all.xml", "All books", &books);
+    feeds.acquisition("newest.xml", "Newest", &newest);
+    feeds.grouped("authors.xml", "By author", "author", &by_author);
+    feeds.grouped("genres.xml", "By genre", "genre", &by_genre);
+    feeds.navigation(
+        "index.xml",
+        &format!("{}'s Library", library.get_owner()),
+        &[
+            NavEntry {
+                title: "All books".to_string(),
+                href: "all.xml".to_string(),
+                content: format!("{} book(s)", books.len()),
+                kind: ACQUISITION,
+            },
+            NavEntry {
+                title: "Newest".to_string(),
+                href: "newest.xml".to_string(),
+                content: "Most recently added books".to_string(),
+                kind: ACQUISITION,
+            },
+            NavEntry {
+                title: "By author".to_string(),
+                href: "authors.xml".to_string(),
+                content: format!("{} author(s)", by_author.len()),
+                kind: NAVIGATION,
+            },
+            NavEntry {
+                title: "By genre".to_string(),
+                href: "genres.xml".to_string(),
+                content: format!("{} genre(s)", by_genre.len()),
+                kind: NAVIGATION,
+            },
+        ],
+    );
+    feeds.files
+}
+
+/// Writes the catalog into `dir`, returning how many files were written.
+pub fn write(library: &Library, dir: &Path) -> Result<usize, io::Error> {
+    write_with_progress(library, dir, &mut |_, _| {})
+}
+
+/// Like `write`, calling `progress` with the files written so far and how
+/// many there are.
+pub fn write_with_progress(
+    library: &Library,
+    dir: &Path,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<usize, io::Error> {
+    fs::create_dir_all(dir)?;
+    let files = catalog(library);
+    for (i, (name, xml)) in files.iter().enumerate() {
+        fs::write(dir.join(name), xml)?;
+        progress(i + 1, files.len());
+    }
+    Ok(files.len())
+}
+
+fn respond(mut stream: TcpStream, library_path: &str) -> Result<(), io::Error> {
+    // A client that connects and sends nothing would otherwise hold up every
+    // other request, as they are handled one at a time.
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or("/");
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let path = match path.trim_start_matches('/') {
+        "" => "index.xml",
+        path => path,
+    };
+
+    // Reload on every request so the feeds follow changes made in the app.
+    let status = match method {
+        "GET" => match Library::from_file(library_path) {
+            Ok(Some(library)) => catalog(&library).remove(path).ok_or("404 Not Found"),
+            Ok(None) => Err("404 Not Found"),
+            Err(err) => {
+                eprintln!("{}", tr!("Couldn't load {}: {}", library_path, err));
+                Err("500 Internal Server Error")
+            }
+        },
+        _ => Err("405 Method Not Allowed"),
+    };
+    let response = match status {
+        Ok(xml) => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/atom+xml; charset=utf-8\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            xml.len(),
+            xml
+        ),
+        Err(status) => format!(
+            "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            status
+        ),
+    };
+    stream.write_all(response.as_bytes())
+}
+
+/// Serves the catalog of the library at `library_path` over HTTP on `addr`
+/// until the process is stopped.
+pub fn serve(library_path: &str, addr: &str) -> Result<(), io::Error> {
+    let listener = TcpListener::bind(addr)?;
+    println!(
+        "{}",
+        tr!("Serving OPDS catalog on http://{}/index.xml", addr)
+    );
+    for stream in listener.incoming() {
+        if let Err(err) = stream.and_then(|stream| respond(stream, library_path)) {
+            eprintln!("{err}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(books: &str) -> Library {
+        let json = format!(r#"{{"owner": "Ana", "books": [{}]}}"#, books);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn book(title: &str, author: &str, isbn: &str) -> String {
+        format!(
+            r#"{{"title": "{}", "author": "{}", "isbn": "{}", "publication_year": 1965,
+                "genre": "ScienceFiction", "status": "Available"}}"#,
+            title, author, isbn
+        )
+    }
+
+    #[test]
+    fn index_links_every_feed() {
+        let library = library(&book("Dune", "Frank Herbert", "9780441172719"));
+        let files = catalog(&library);
+        let index = &files["index.xml"];
+        assert!(index.contains("<title>Ana&#39;s Library</title>"));
+        for href in ["all.xml", "newest.xml", "authors.xml", "genres.xml"] {
+            assert!(index.contains(&format!("<link rel=\"subsection\" href=\"{}\"", href)));
+            assert!(files.contains_key(href));
+        }
+        assert!(files["genres.xml"].contains("href=\"genre-science-fiction.xml\""));
+    }
+
+    #[test]
+    fn feeds_list_books_by_title_and_newest_first() {
+        let library = library(
+            &[
+                book("Ubik", "Philip K. Dick", "9780547572291"),
+                book("Dune", "Frank Herbert", "9780441172719"),
+            ]
+            .join(","),
+        );
+        let files = catalog(&library);
+        let all = &files["all.xml"];
+        assert!(all.find("<title>Dune</title>") < all.find("<title>Ubik</title>"));
+        let newest = &files["newest.xml"];
+        assert!(newest.find("<title>Dune</title>") < newest.find("<title>Ubik</title>"));
+    }
+
+    #[test]
+    fn entries_are_escaped_and_identified_by_isbn() {
+        let library = library(&book(
+            "Pride & <Prejudice>",
+            "Austen \\\"Jane\\\"",
+            "9780141439518",
+        ));
+        let entry = &catalog(&library)["entry-9780141439518.xml"];
+        assert!(entry.contains("<title>Pride &amp; &lt;Prejudice&gt;</title>"));
+        assert!(entry.contains("<author><name>Austen &quot;Jane&quot;</name></author>"));
+        assert!(entry.contains("<id>urn:isbn:9780141439518</id>"));
+        assert!(entry.contains("<dc:identifier>urn:isbn:9780141439518</dc:identifier>"));
+        assert!(entry.contains("<link rel=\"alternate\" href=\"entry-9780141439518.xml\""));
+        assert!(!entry.contains("acquisition"));
+    }
+
+    #[test]
+    fn raw_isbns_are_not_urns() {
+        let library = library(&book("Dune", "Frank Herbert", "n/a"));
+        let entry = &catalog(&library)["entry-n-a.xml"];
+        assert!(entry.contains("<id>urn:libros:ana:entry-n-a.xml</id>"));
+        assert!(!entry.contains("urn:isbn"));
+        assert!(!entry.contains("dc:identifier"));
+    }
+
+    #[test]
+    fn colliding_authors_get_their_own_feeds() {
+        let library = library(
+            &[
+                book("Dune", "Smith, J.", "9780441172719"),
+                book("Emma", "Smith J", "9780141439587"),
+                book("Ubik", "???", "9780547572291"),
+            ]
+            .join(","),
+        );
+        let files = catalog(&library);
+        assert!(files["author-smith-j.xml"].contains("<title>Smith J</title>"));
+        assert!(files["author-smith-j-2.xml"].contains("<title>Smith, J.</title>"));
+        assert!(files["author-unknown.xml"].contains("<title>???</title>"));
+        let authors = &files["authors.xml"];
+        for href in [
+            "author-smith-j.xml",
+            "author-smith-j-2.xml",
+            "author-unknown.xml",
+        ] {
+            assert!(authors.contains(&format!("href=\"{}\"", href)));
+        }
+    }
+
+    #[test]
+    fn raw_isbns_get_their_own_entries() {
+        let library = library(&[book("Dune", "A", "n/a"), book("Emma", "B", "??")].join(","));
+        let files = catalog(&library);
+        assert!(files["entry-n-a.xml"].contains("<title>Dune</title>"));
+        assert!(files["entry-book.xml"].contains("<title>Emma</title>"));
+        assert!(!files.contains_key("entry-.xml"));
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::library::{Book, Genre, Library, Status};
+
+/// How many titles the dashboard ranks.
+const TOP_BORROWED: usize = 5;
+/// How many months of checkouts the dashboard charts.
+const MONTHS: u32 = 12;
+
+/// Figures about the collection and its loans, for the dashboard.
+#[derive(Debug)]
+pub struct Stats {
+    pub books: usize,
+    pub available: usize,
+    pub by_genre: Vec<(Genre, u64)>,
+    pub by_status: Vec<(Status, u64)>,
+    /// Books per decade of publication, oldest first, including empty
+    /// decades in between. Books without a year are left out.
+    pub by_decade: Vec<(u16, u64)>,
+    /// The most borrowed titles, most first.
+    pub top_borrowed: Vec<(String, u64)>,
+    /// Checkouts in each of the last months, oldest first, keyed by the
+    /// first day of the month.
+    pub monthly_checkouts: Vec<(NaiveDate, u64)>,
+}
+
+impl Stats {
+    pub fn new(library: &Library, today: NaiveDate) -> Stats {
+        let books = library.get_books();
+        let count = |matches: &dyn Fn(&Book) -> bool| {
+            books.iter().filter(|book| matches(book)).count() as u64
+        };
+        let by_genre = Genre::ALL
+            .iter()
+            .map(|genre| (genre.clone(), count(&|book| book.get_genre() == genre)))
+            .collect();
+        let by_status = Status::ALL
+            .iter()
+            .map(|status| (status.clone(), count(&|book| book.get_status() == status)))
+            .collect();
+
+        let mut decades = BTreeMap::new();
+        for book in books {
+            match book.get_publication_year() {
+                0 => {}
+                year => *decades.entry(year / 10 * 10).or_insert(0) += 1,
+            }
+        }
+        let by_decade = match (decades.keys().next(), decades.keys().next_back()) {
+            (Some(&first), Some(&last)) => (first..=last)
+                .step_by(10)
+                .map(|decade| (decade, decades.get(&decade).copied().unwrap_or(0)))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut loans = HashMap::new();
+        for loan in library.get_loans() {
+            *loans.entry(&loan.isbn).or_insert(0) += 1;
+        }
+        let mut borrowed: Vec<(String, u64)> = books
+            .iter()
+            .filter_map(|book| {
+                let count = *loans.get(book.get_isbn())?;
+                Some((book.get_title().to_string(), count))
+            })
+            .collect();
+        borrowed.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        borrowed.truncate(TOP_BORROWED);
+
+        let this_month = today.with_day(1).unwrap();
+        let monthly_checkouts = (0..MONTHS)
+            .rev()
+            .map(|ago| {
+                let month = this_month - Months::new(ago);
+                let checkouts = library
+                    .get_loans()
+                    .iter()
+                    .filter(|loan| loan.checked_out.with_day(1).unwrap() == month)
+                    .count() as u64;
+                (month, checkouts)
+            })
+            .collect();
+
+        Stats {
+            books: books.len(),
+            available: books.iter().filter(|book| book.get_available()).count(),
+            by_genre,
+            by_status,
+            by_decade,
+            top_borrowed: borrowed,
+            monthly_checkouts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn counts<T>(pairs: &[(T, u64)]) -> Vec<u64> {
+        pairs.iter().map(|(_, count)| *count).collect()
+    }
+
+    #[test]
+    fn empty_library() {
+        let stats = Stats::new(&Library::new("Ana"), date(2024, 3, 14));
+        assert_eq!((stats.books, stats.available), (0, 0));
+        assert!(stats.by_genre.iter().all(|(_, count)| *count == 0));
+        assert!(stats.by_status.iter().all(|(_, count)| *count == 0));
+        assert!(stats.by_decade.is_empty());
+        assert!(stats.top_borrowed.is_empty());
+        assert_eq!(stats.monthly_checkouts.len(), 12);
+        assert!(stats.monthly_checkouts.iter().all(|(_, count)| *count == 0));
+    }
+
+    #[test]
+    fn aggregates_books_and_loans() {
+        let library: Library = serde_json::from_str(include_str!("testdata/library.json")).unwrap();
+        let stats = Stats::new(&library, date(2024, 3, 14));
+        assert_eq!((stats.books, stats.available), (6, 3));
+        assert_eq!(counts(&stats.by_genre), [2, 1, 2, 1, 0]);
+        assert_eq!(counts(&stats.by_status), [3, 2, 1]);
+        // 1902 to 2011, with the empty decades in between.
+        assert_eq!(stats.by_decade.first(), Some(&(1900, 1)));
+        assert_eq!(stats.by_decade.last(), Some(&(2010, 1)));
+        assert_eq!(
+            counts(&stats.by_decade),
+            [1, 0, 1, 0, 1, 0, 2, 0, 0, 0, 0, 1]
+        );
+        let titles: Vec<&str> = stats.top_borrowed.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["1984", "The Great Gatsby", "To Kill a Mockingbird"]
+        );
+        assert_eq!(stats.monthly_checkouts[0], (date(2023, 4, 1), 0));
+        assert_eq!(
+            stats.monthly_checkouts[9..],
+            [
+                (date(2024, 1, 1), 1),
+                (date(2024, 2, 1), 1),
+                (date(2024, 3, 1), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn checkouts_older_than_a_year_are_left_out() {
+        let library: Library = serde_json::from_str(include_str!("testdata/library.json")).unwrap();
+        let stats = Stats::new(&library, date(2025, 1, 31));
+        assert_eq!(stats.monthly_checkouts[0], (date(2024, 2, 1), 1));
+        assert_eq!(
+            stats.monthly_checkouts.iter().map(|(_, n)| n).sum::<u64>(),
+            2
+        );
+    }
+}
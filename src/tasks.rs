@@ -0,0 +1,163 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::app::recovery_path;
+use crate::calibre::{self, CalibreBooks};
+use crate::i18n::{t, tr};
+use crate::library::Library;
+use crate::{html, opds};
+
+/// Slow work that runs on a worker thread so the screen keeps updating.
+/// Exports get a copy of the library as it was when they started.
+#[derive(Debug)]
+pub enum Task {
+    Load(String),
+    /// Opens another library file, if it exists.
+    Open(String),
+    ImportCalibre(PathBuf),
+    ExportHtml(Library, PathBuf),
+    ExportOpds(Library, PathBuf),
+}
+
+/// What a finished task hands back.
+#[derive(Debug)]
+pub enum Outcome {
+    /// `None` when there was no library file yet; `true` when the library
+    /// was restored from a recovery file.
+    Loaded(Result<Option<Library>, String>, bool),
+    /// The path and the library there, `None` when there was none yet, and
+    /// whether it was restored from a recovery file.
+    Opened(String, Result<Option<Library>, String>, bool),
+    /// Books to merge into the library, which only the app thread touches.
+    Read(Result<CalibreBooks, String>),
+    Exported(Result<String, String>),
+    /// The task panicked, with the panic message.
+    Crashed(String),
+}
+
+#[derive(Debug)]
+enum Update {
+    Progress(usize, usize),
+    Done(Outcome),
+}
+
+impl Task {
+    fn label(&self) -> &'static str {
+        match self {
+            Task::Load(_) => t("Loading the library"),
+            Task::Open(_) => t("Opening the library"),
+            Task::ImportCalibre(_) => t("Importing from Calibre"),
+            Task::ExportHtml(..) => t("Exporting the HTML site"),
+            Task::ExportOpds(..) => t("Exporting the OPDS feeds"),
+        }
+    }
+
+    fn run(self, progress: &mut dyn FnMut(usize, usize)) -> Outcome {
+        match self {
+            Task::Load(path) => {
+                let (library, restored) = load(&path, progress);
+                Outcome::Loaded(library, restored)
+            }
+            Task::Open(path) => {
+                let (library, restored) = load(&path, progress);
+                Outcome::Opened(path, library, restored)
+            }
+            Task::ImportCalibre(path) => Outcome::Read(
+                calibre::read(&path, progress).map_err(|e| tr!("Couldn't import: {}", e)),
+            ),
+            Task::ExportHtml(library, dir) => Outcome::Exported(
+                html::export_with_progress(&library, &dir, progress)
+                    .map(|_| tr!("Wrote the HTML site to {}", dir.display()))
+                    .map_err(|e| tr!("Couldn't export: {}", e)),
+            ),
+            Task::ExportOpds(library, dir) => Outcome::Exported(
+                opds::write_with_progress(&library, &dir, progress)
+                    .map(|written| tr!("Wrote {} feed(s) to {}", written, dir.display()))
+                    .map_err(|e| tr!("Couldn't export: {}", e)),
+            ),
+        }
+    }
+
+    /// Starts the task on a new thread.
+    pub fn spawn(self) -> Running {
+        let label = self.label();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let progress = sender.clone();
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.run(&mut |done, total| {
+                    let _ = progress.send(Update::Progress(done, total));
+                })
+            }))
+            .unwrap_or_else(|payload| Outcome::Crashed(panic_message(payload)));
+            let _ = sender.send(Update::Done(outcome));
+        });
+        Running {
+            label,
+            done: 0,
+            total: 0,
+            receiver,
+        }
+    }
+}
+
+/// Reads the library at `path` the same way for loading and opening. When
+/// an earlier session left unsaved changes in a recovery file, reads that
+/// instead and returns `true`.
+fn load(
+    path: &str,
+    progress: &mut dyn FnMut(usize, usize),
+) -> (Result<Option<Library>, String>, bool) {
+    let recovery = recovery_path(path);
+    let (file, restored) = match Path::new(&recovery).is_file() {
+        true => (recovery.as_str(), true),
+        false => (path, false),
+    };
+    let library = Library::from_file_with_progress(file, progress)
+        .map_err(|e| tr!("Couldn't open {}: {}", file, e));
+    (library, restored)
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => String::new(),
+        },
+    }
+}
+
+/// A task on its worker thread, with the progress it last reported.
+#[derive(Debug)]
+pub struct Running {
+    pub label: &'static str,
+    pub done: usize,
+    pub total: usize,
+    receiver: Receiver<Update>,
+}
+
+impl Running {
+    /// Takes in the updates sent so far, returning the outcome once the
+    /// task is done.
+    pub fn poll(&mut self) -> Option<Outcome> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Update::Progress(done, total)) => (self.done, self.total) = (done, total),
+                Ok(Update::Done(outcome)) => return Some(outcome),
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// How far along the task is, from 0 to 1; 0 until it reports progress.
+    pub fn ratio(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => (self.done as f64 / total as f64).min(1.0),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::io;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crossterm::cursor::Show;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use signal_hook::consts::TERM_SIGNALS;
+use signal_hook::flag;
+
+/// Switches the terminal to the app's full screen, raw mode.
+pub fn setup() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        io::stderr(),
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )
+}
+
+/// Gives the terminal back the way the shell had it. Safe to call more
+/// than once.
+pub fn restore() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        io::stderr(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        Show
+    )
+}
+
+/// Restores the terminal before a panic message is printed, so it shows up
+/// on a usable screen. Panics on worker threads are reported by their task
+/// instead, as printing them would garble the app's screen.
+pub fn install_panic_hook() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if thread::current().name() != Some("main") {
+            return;
+        }
+        let _ = restore();
+        default(info);
+    }));
+}
+
+/// Signals caught while the app runs, checked on every pass of the loop.
+pub struct Signals {
+    terminate: Arc<AtomicBool>,
+    suspend: Arc<AtomicBool>,
+}
+
+impl Signals {
+    pub fn register() -> io::Result<Signals> {
+        let signals = Signals {
+            terminate: Arc::new(AtomicBool::new(false)),
+            suspend: Arc::new(AtomicBool::new(false)),
+        };
+        for signal in TERM_SIGNALS {
+            flag::register(*signal, Arc::clone(&signals.terminate))?;
+        }
+        #[cfg(unix)]
+        flag::register(signal_hook::consts::SIGTSTP, Arc::clone(&signals.suspend))?;
+        Ok(signals)
+    }
+
+    /// Whether the app was asked to stop, e.g. with SIGTERM.
+    pub fn terminated(&self) -> bool {
+        self.terminate.load(Ordering::Relaxed)
+    }
+
+    /// Whether the app was asked to suspend since the last call.
+    pub fn take_suspend(&self) -> bool {
+        self.suspend.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Hands the terminal back to the shell and stops the process until it's
+/// resumed, e.g. with `fg`.
+#[cfg(unix)]
+pub fn suspend() -> io::Result<()> {
+    restore()?;
+    signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)?;
+    setup()
+}
+
+#[cfg(not(unix))]
+pub fn suspend() -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,229 @@
+use std::env;
+use std::fmt;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::t;
+use crate::library::{Book, Status};
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone, Copy)]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// Blue and orange instead of green and red, with status symbols.
+    ColorBlind,
+    HighContrast,
+    /// No colour at all; status is told apart by symbols.
+    Monochrome,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 4] = [
+        ThemeName::Default,
+        ThemeName::ColorBlind,
+        ThemeName::HighContrast,
+        ThemeName::Monochrome,
+    ];
+}
+
+impl fmt::Display for ThemeName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeName::Default => f.write_str(t("Default")),
+            ThemeName::ColorBlind => f.write_str(t("Color blind")),
+            ThemeName::HighContrast => f.write_str(t("High contrast")),
+            ThemeName::Monochrome => f.write_str(t("Monochrome")),
+        }
+    }
+}
+
+/// `name`, unless `no_color` (the value of `NO_COLOR`) asks for no colour.
+fn chosen(name: ThemeName, no_color: Option<&str>) -> ThemeName {
+    match no_color {
+        Some(value) if !value.is_empty() => ThemeName::Monochrome,
+        _ => name,
+    }
+}
+
+/// The styles every screen draws with.
+#[derive(Debug)]
+pub struct Theme {
+    pub title: Style,
+    pub available: Style,
+    pub unavailable: Style,
+    /// Search matches.
+    pub highlight: Style,
+    /// The filter field being edited.
+    pub focus: Style,
+    /// The selected table row.
+    pub selected: Style,
+    pub home_mode: Style,
+    pub search_mode: Style,
+    pub filter_mode: Style,
+    pub book_mode: Style,
+    pub text: Style,
+    pub muted: Style,
+    pub notice: Style,
+    pub info: Style,
+    pub error: Style,
+    pub filters: Style,
+    pub hint: Style,
+    /// Whether status is also shown as a symbol, for when colour alone
+    /// can't tell books apart.
+    pub symbols: bool,
+}
+
+impl Theme {
+    /// The theme called `name`, or monochrome when `NO_COLOR` is set.
+    pub fn load(name: ThemeName) -> Theme {
+        Theme::new(chosen(name, env::var("NO_COLOR").ok().as_deref()))
+    }
+
+    pub fn new(name: ThemeName) -> Theme {
+        let fg = |color| Style::default().fg(color);
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        match name {
+            ThemeName::Default => Theme {
+                title: fg(Color::White).bg(Color::Black),
+                available: fg(Color::Green),
+                unavailable: fg(Color::Red),
+                highlight: fg(Color::Yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                focus: fg(Color::Yellow).add_modifier(Modifier::REVERSED),
+                selected: Style::default().add_modifier(Modifier::REVERSED),
+                home_mode: fg(Color::Green),
+                search_mode: fg(Color::Yellow),
+                filter_mode: fg(Color::Magenta),
+                book_mode: fg(Color::Cyan),
+                text: fg(Color::White),
+                muted: fg(Color::DarkGray),
+                notice: fg(Color::Yellow),
+                info: fg(Color::LightBlue),
+                error: fg(Color::Red),
+                filters: fg(Color::Magenta),
+                hint: fg(Color::Green),
+                symbols: false,
+            },
+            // Okabe-Ito colours, picked from the 256-colour palette.
+            ThemeName::ColorBlind => {
+                let blue = Color::Indexed(32);
+                let sky = Color::Indexed(74);
+                let orange = Color::Indexed(214);
+                let vermillion = Color::Indexed(166);
+                Theme {
+                    title: fg(Color::White).bg(Color::Black),
+                    available: fg(sky),
+                    unavailable: fg(orange),
+                    highlight: bold.add_modifier(Modifier::UNDERLINED),
+                    focus: fg(sky).add_modifier(Modifier::REVERSED),
+                    selected: Style::default().add_modifier(Modifier::REVERSED),
+                    home_mode: fg(sky),
+                    search_mode: fg(orange),
+                    filter_mode: fg(blue),
+                    book_mode: fg(sky),
+                    text: Style::default(),
+                    muted: fg(Color::Gray),
+                    notice: fg(orange),
+                    info: fg(sky),
+                    error: fg(vermillion).add_modifier(Modifier::BOLD),
+                    filters: fg(blue),
+                    hint: fg(sky),
+                    symbols: true,
+                }
+            }
+            ThemeName::HighContrast => Theme {
+                title: bold.fg(Color::Black).bg(Color::White),
+                available: bold.fg(Color::LightGreen),
+                unavailable: bold.fg(Color::LightRed),
+                highlight: bold.fg(Color::Black).bg(Color::LightYellow),
+                focus: bold.fg(Color::Black).bg(Color::LightYellow),
+                selected: bold.add_modifier(Modifier::REVERSED),
+                home_mode: bold.fg(Color::LightGreen),
+                search_mode: bold.fg(Color::LightYellow),
+                filter_mode: bold.fg(Color::LightMagenta),
+                book_mode: bold.fg(Color::LightCyan),
+                text: fg(Color::White),
+                muted: fg(Color::White),
+                notice: bold.fg(Color::LightYellow),
+                info: bold.fg(Color::LightCyan),
+                error: bold.fg(Color::LightRed),
+                filters: bold.fg(Color::LightMagenta),
+                hint: fg(Color::White),
+                symbols: true,
+            },
+            ThemeName::Monochrome => Theme {
+                title: bold,
+                available: Style::default(),
+                unavailable: Style::default().add_modifier(Modifier::DIM),
+                highlight: bold.add_modifier(Modifier::UNDERLINED),
+                focus: Style::default().add_modifier(Modifier::REVERSED),
+                selected: Style::default().add_modifier(Modifier::REVERSED),
+                home_mode: bold,
+                search_mode: bold,
+                filter_mode: bold,
+                book_mode: bold,
+                text: Style::default(),
+                muted: Style::default(),
+                notice: bold,
+                info: Style::default(),
+                error: bold,
+                filters: Style::default().add_modifier(Modifier::ITALIC),
+                hint: Style::default(),
+                symbols: true,
+            },
+        }
+    }
+
+    pub fn availability(&self, book: &Book) -> Style {
+        match book.get_available() {
+            true => self.available,
+            false => self.unavailable,
+        }
+    }
+
+    /// The status as shown on screen, with a symbol when the theme uses them.
+    pub fn status(&self, status: &Status) -> String {
+        if !self.symbols {
+            return status.to_string();
+        }
+        let symbol = match status {
+            Status::Available => "✓",
+            Status::CheckedOut => "✗",
+            Status::Lost => "?",
+        };
+        format!("{} {}", symbol, status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_means_monochrome() {
+        assert_eq!(
+            chosen(ThemeName::ColorBlind, Some("1")),
+            ThemeName::Monochrome
+        );
+        assert_eq!(
+            chosen(ThemeName::ColorBlind, Some("")),
+            ThemeName::ColorBlind
+        );
+        assert_eq!(
+            chosen(ThemeName::HighContrast, None),
+            ThemeName::HighContrast
+        );
+    }
+
+    #[test]
+    fn status_symbols_follow_the_theme() {
+        assert!(!Theme::new(ThemeName::Default).symbols);
+        assert!(Theme::new(ThemeName::ColorBlind).symbols);
+        assert!(Theme::new(ThemeName::HighContrast).symbols);
+        assert!(Theme::new(ThemeName::Monochrome).symbols);
+        assert_eq!(
+            Theme::new(ThemeName::Monochrome).available,
+            Style::default()
+        );
+    }
+}
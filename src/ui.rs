@@ -1,233 +1,1556 @@
+use chrono::Datelike;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    layout::{Constraint, Direction, Layout, Margin, Position, Rect},
+    style::{Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, Padding, Paragraph, Wrap},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Cell, Clear, Gauge, Padding, Paragraph, Row,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline, Table, Wrap,
+    },
     Frame,
 };
 
-use crate::app::{App, CurrentScreen};
+use crate::app::{App, Column, CurrentScreen, FilterField, SortOrder, Tab};
+use crate::commands::{describe, Command};
+use crate::i18n::{t, tr};
+use crate::isbn::Isbn;
+use crate::keymap::{Action, Key, KeyContext};
+use crate::library::{Book, Library, LibrarySearchCriteria, Status};
+use crate::notifications::Level;
+use crate::stats::Stats;
+use unicode_width::UnicodeWidthStr;
 
+/// Smaller than this, nothing is drawn but a note asking for more room.
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 14;
+/// Narrower than this, the catalog drops its side pane.
+const COMPACT_WIDTH: u16 = 80;
+/// From this width on, the books out on loan get a pane of their own.
+const WIDE_WIDTH: u16 = 150;
+/// Popups never shrink below this while the terminal is larger.
+const POPUP_MIN_WIDTH: u16 = 40;
+const POPUP_MIN_HEIGHT: u16 = 7;
+/// Widest a toast gets, and the most lines of text it shows.
+const TOAST_WIDTH: u16 = 48;
+const TOAST_LINES: u16 = 3;
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// How the main screen is laid out, picked by the terminal width.
+#[derive(PartialEq, Clone, Copy)]
+enum Breakpoint {
+    /// One column: the catalog or the book, nothing beside it.
+    Compact,
+    /// The catalog with the selected book's details beside it.
+    Normal,
+    /// Catalog, details and the books out on loan.
+    Wide,
+}
+
+impl Breakpoint {
+    fn of(area: Rect) -> Breakpoint {
+        match area.width {
+            width if width < COMPACT_WIDTH => Breakpoint::Compact,
+            width if width < WIDE_WIDTH => Breakpoint::Normal,
+            _ => Breakpoint::Wide,
+        }
+    }
+}
+
+/// A `percent_x` by `percent_y` rect centered in `r`, but no smaller than a
+/// readable popup unless `r` itself is.
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
+    let scale = |length: u16, percent: u16| (length as u32 * percent as u32 / 100) as u16;
+    let width = scale(r.width, percent_x).max(POPUP_MIN_WIDTH).min(r.width);
+    let height = scale(r.height, percent_y)
+        .max(POPUP_MIN_HEIGHT)
+        .min(r.height);
+    Rect::new(
+        r.x + (r.width - width) / 2,
+        r.y + (r.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Renders `items` as centered lines, wrapping between items when they don't
+/// fit; the ones with a key become click targets for that key.
+fn clickable_line(
+    frame: &mut Frame,
+    app: &mut App,
+    area: Rect,
+    items: Vec<(Span<'static>, Option<Key>)>,
+    separator: Span<'static>,
+) {
+    let mut rows: Vec<Vec<(Span, Option<Key>)>> = vec![Vec::new()];
+    let mut row_width = 0;
+    for (span, key) in items {
+        let width = span.width() + separator.width();
+        if row_width > 0 && row_width + span.width() > area.width as usize {
+            rows.push(Vec::new());
+            row_width = 0;
+        }
+        row_width += width;
+        rows.last_mut().unwrap().push((span, key));
+    }
+
+    let mut lines = Vec::new();
+    for (y, row) in (area.y..area.bottom()).zip(rows) {
+        let mut spans = Vec::new();
+        for (i, (span, _)) in row.iter().enumerate() {
+            if i > 0 {
+                spans.push(separator.clone());
+            }
+            spans.push(span.clone());
+        }
+        let line = Line::from(spans);
+        // Same offset the centered paragraph uses.
+        let mut x = area.x + (area.width / 2).saturating_sub(line.width() as u16 / 2);
+        for (span, key) in row {
+            let width = span.width() as u16;
+            if let Some(key) = key {
+                let target = Rect::new(x, y, width, 1).intersection(area);
+                app.click_targets.push((target, key));
+            }
+            x += width + separator.width() as u16;
+        }
+        lines.push(line);
+    }
+    frame.render_widget(Paragraph::new(lines).centered(), area);
 }
 
-fn popup_screen(frame: &mut Frame, title: &str, message: &str, borders: Borders) {
+fn popup_screen(
+    frame: &mut Frame,
+    app: &mut App,
+    title: &str,
+    message: &str,
+    buttons: &[(&'static str, Action)],
+) -> Rect {
     frame.render_widget(Clear, frame.area());
     let popup_block = Block::default()
         .title(title)
         .title_alignment(ratatui::layout::Alignment::Center)
-        .borders(borders)
-        .padding(Padding::new(0, 0, 1, 1))
+        .borders(Borders::ALL)
+        .padding(Padding::new(0, 0, 1, 0))
         .style(Style::default());
 
+    let area = centered_rect(60, 25, frame.area());
+    let inner = popup_block.inner(area);
+    frame.render_widget(popup_block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
     let text = Text::styled(message, Style::default());
-    let paragraph = Paragraph::new(text).block(popup_block).centered();
+    frame.render_widget(
+        Paragraph::new(text).centered().wrap(Wrap { trim: true }),
+        rows[0],
+    );
 
-    let area = centered_rect(60, 25, frame.area());
-    frame.render_widget(paragraph, area);
+    let buttons = buttons
+        .iter()
+        .map(|(label, action)| {
+            let button = Span::styled(
+                format!("[ {} ]", label),
+                Style::default().add_modifier(Modifier::BOLD),
+            );
+            (button, app.keymap.key(*action))
+        })
+        .collect();
+    clickable_line(frame, app, rows[1], buttons, Span::raw("  "));
+    rows[0]
+}
+
+/// How hints and prompts name the key for `action`.
+fn key_name(app: &App, action: Action) -> String {
+    app.keymap
+        .key(action)
+        .map_or(t("(unbound)").to_string(), |key| key.symbol())
 }
 
-fn loading_screen(frame: &mut Frame) {
+fn loading_screen(frame: &mut Frame, app: &mut App) {
+    if let Some(task) = &app.task {
+        let (label, ratio) = (task_status(app).unwrap(), task.ratio());
+        let area = popup_screen(frame, app, &label, "", &[(t("Cancel"), Action::Back)]);
+        let gauge_area = Rect::new(
+            area.x + 2,
+            area.y + area.height / 2,
+            area.width.saturating_sub(4),
+            1,
+        );
+        frame.render_widget(
+            Gauge::default()
+                .ratio(ratio)
+                .gauge_style(app.theme.info)
+                .use_unicode(true),
+            gauge_area,
+        );
+        return;
+    }
+    let title = tr!(
+        "Loading library, press {} and wait...",
+        key_name(app, Action::Confirm)
+    );
+    let message = tr!("You can cancel by pressing {}", key_name(app, Action::Back));
     popup_screen(
         frame,
-        "Loading library, press Enter and wait...",
-        "You can cancel by pressing ESC",
-        Borders::ALL,
+        app,
+        &title,
+        &message,
+        &[(t("Load"), Action::Confirm), (t("Cancel"), Action::Back)],
     );
 }
 
 fn new_owner_screen(frame: &mut Frame, app: &App) {
-    let owner_text = format!(
-        "Enter the owner of the library: {}",
-        app.owner_input.clone()
-    );
-    popup_screen(frame, "New Library", &owner_text, Borders::ALL);
-}
+    let area = centered_rect(60, 25, frame.area());
+    let block = Block::default()
+        .title(t("New Library"))
+        .title_alignment(ratatui::layout::Alignment::Center)
+        .borders(Borders::ALL)
+        .padding(Padding::new(2, 2, 1, 0));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
 
-fn checked_out_result_screen(frame: &mut Frame, app: &App) {
-    let result_text = match app.checkout_success.as_ref() {
-        Some(Err(e)) => &format!("Error: {}\nPress Enter", e),
-        Some(Ok(_)) => "Success",
-        None => "Nothing happened",
-    };
-    popup_screen(frame, "Checkout Result", result_text, Borders::ALL);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Length(1)])
+        .split(inner);
+    frame.render_widget(
+        Paragraph::new(t("Enter the owner of the library:"))
+            .centered()
+            .wrap(Wrap { trim: true }),
+        rows[0],
+    );
+    app.owner_input.render(
+        frame,
+        rows[1],
+        Style::default().add_modifier(Modifier::UNDERLINED),
+        true,
+    );
 }
 
-fn exiting_screen(frame: &mut Frame) {
+fn exiting_screen(frame: &mut Frame, app: &mut App) {
+    let message = tr!(
+        "Are you sure you want to exit? ({}/{})",
+        key_name(app, Action::Yes),
+        key_name(app, Action::No)
+    );
     popup_screen(
         frame,
-        "Exiting Library Management Tool",
-        "Are you sure you want to exit? (y/n)",
-        Borders::ALL,
+        app,
+        t("Exiting Library Management Tool"),
+        &message,
+        &[(t("Yes"), Action::Yes), (t("No"), Action::No)],
     );
 }
 
-fn main_screen_title_bar(frame: &mut Frame, app: &App, area: Rect) {
-    let title = format!(
-        "Library Management Tool - {}'s Library",
-        app.library.as_ref().unwrap().get_owner()
-    );
-    let title_text = Text::styled(title, Style::default().fg(Color::White).bg(Color::Black));
-    let title_paragraph = Paragraph::new(title_text)
-        .block(Block::default().borders(Borders::ALL))
-        .centered();
-    frame.render_widget(title_paragraph, area);
+/// The library's name over the tab bar. Tabs can be clicked while the main
+/// screen itself is shown.
+fn main_screen_title_bar(frame: &mut Frame, app: &mut App, area: Rect) {
+    let owner = app.library.as_ref().unwrap().get_owner();
+    let title = match Breakpoint::of(area) {
+        Breakpoint::Compact => tr!("{}'s Library", owner),
+        _ => tr!("Library Management Tool - {}'s Library", owner),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Line::styled(title, app.theme.title).centered());
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let clickable = matches!(app.current_screen, CurrentScreen::Home);
+    let mut spans = Vec::new();
+    let mut x = inner.x;
+    for (i, tab) in Tab::ALL.into_iter().enumerate() {
+        if i > 0 {
+            let divider = Span::styled("|", app.theme.muted);
+            x += divider.width() as u16;
+            spans.push(divider);
+        }
+        let style = match tab == app.tab {
+            true => app.theme.selected,
+            false => app.theme.text,
+        };
+        let label = Span::styled(format!(" {} ", tab), style);
+        let width = label.width() as u16;
+        if clickable {
+            let target = Rect::new(x, inner.y, width, 1).intersection(inner);
+            app.tab_areas.push((target, tab));
+        }
+        x += width;
+        spans.push(label);
+    }
+    let tabs = Line::from(spans);
+
+    // Background work, unsaved changes and the time on the right, leaving
+    // out the first ones when they don't fit.
+    let mut status = Vec::new();
+    if let Some(task) = task_status(app) {
+        status.push(Span::styled(task, app.theme.info));
+    }
+    if app.unsaved_since.is_some() {
+        status.push(Span::styled(t("Unsaved changes"), app.theme.muted));
+    }
+    status.push(Span::styled(
+        app.now().format("%H:%M ").to_string(),
+        app.theme.text,
+    ));
+    let room = (inner.width as usize).saturating_sub(tabs.width() + 1);
+    while status.iter().map(|span| span.width() + 2).sum::<usize>() > room {
+        status.remove(0);
+    }
+    let mut right = Vec::new();
+    for span in status {
+        right.push(Span::raw("  "));
+        right.push(span);
+    }
+    frame.render_widget(tabs, inner);
+    frame.render_widget(Line::from(right).right_aligned(), inner);
+}
+
+/// A spinner, the running task and how far along it is.
+fn task_status(app: &App) -> Option<String> {
+    let task = app.task.as_ref()?;
+    let spinner = SPINNER[app.ticks % SPINNER.len()];
+    Some(match task.total {
+        0 => format!("{} {}", spinner, task.label),
+        _ => format!("{} {} {:.0}%", spinner, task.label, task.ratio() * 100.0),
+    })
+}
+
+fn column_width(column: Column) -> Constraint {
+    match column {
+        Column::Title => Constraint::Min(12),
+        Column::Author => Constraint::Min(8),
+        Column::Isbn => Constraint::Max(13),
+        Column::Year => Constraint::Max(6),
+        Column::Genre => Constraint::Max(15),
+        Column::Status => Constraint::Max(13),
+    }
+}
+
+/// Byte range of the first case-insensitive occurrence of `query` in `text`.
+fn find_ignore_case(text: &str, query: &str) -> Option<(usize, usize)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+    for (start, _) in text.char_indices() {
+        let mut matched = 0;
+        let mut end = start;
+        for c in text[start..].chars() {
+            let lower: Vec<char> = c.to_lowercase().collect();
+            if query.get(matched..matched + lower.len()) != Some(&lower[..]) {
+                break;
+            }
+            matched += lower.len();
+            end += c.len_utf8();
+            if matched == query.len() {
+                return Some((start, end));
+            }
+        }
+    }
+    None
+}
+
+fn highlighted(text: String, query: &str, style: Style) -> Line<'static> {
+    match find_ignore_case(&text, query) {
+        Some((start, end)) => Line::from(vec![
+            Span::raw(text[..start].to_string()),
+            Span::styled(text[start..end].to_string(), style),
+            Span::raw(text[end..].to_string()),
+        ]),
+        None => Line::from(text),
+    }
+}
+
+/// The column the search runs on and the text to highlight in it.
+fn search_highlight(app: &App) -> Option<(Column, String)> {
+    if !app.search_active() {
+        return None;
+    }
+    let query = app.search_query.clone();
+    Some(match app.searching_criteria {
+        LibrarySearchCriteria::Title => (Column::Title, query),
+        LibrarySearchCriteria::Author => (Column::Author, query),
+        LibrarySearchCriteria::ISBN => match Isbn::parse(&query) {
+            Ok(isbn) => (Column::Isbn, isbn.to_string()),
+            Err(_) => (
+                Column::Isbn,
+                query
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric())
+                    .collect(),
+            ),
+        },
+    })
+}
+
+fn main_screen_content(frame: &mut Frame, app: &mut App, area: Rect) {
+    let columns = app.config.columns.clone();
+
+    let header = Row::new(columns.iter().map(|column| {
+        let arrow = match app.config.sort {
+            Some((sorted, SortOrder::Ascending)) if sorted == *column => " ▲",
+            Some((sorted, SortOrder::Descending)) if sorted == *column => " ▼",
+            _ => "",
+        };
+        Cell::from(format!("{}{}", column, arrow))
+    }))
+    .style(Style::default().add_modifier(Modifier::BOLD));
+
+    let highlight = search_highlight(app);
+    let books = app.catalog_books();
+    let book_count = books.len();
+    let rows: Vec<Row> = books
+        .iter()
+        .map(|book| {
+            Row::new(columns.iter().map(|column| match &highlight {
+                Some((searched, query)) if searched == column => {
+                    Cell::from(highlighted(column.cell(book), query, app.theme.highlight))
+                }
+                _ if *column == Column::Status => Cell::from(app.theme.status(book.get_status())),
+                _ => Cell::from(column.cell(book)),
+            }))
+            .style(app.theme.availability(book))
+        })
+        .collect();
+
+    let table = Table::new(rows, columns.iter().map(|column| column_width(*column)))
+        .header(header)
+        .row_highlight_style(app.theme.selected)
+        .highlight_symbol("> ");
+    scrolled_table(frame, app, area, table, book_count);
+}
+
+/// Renders the current tab's table with a scrollbar when its `row_count`
+/// rows don't fit, and records where the rows went for paging and clicks.
+fn scrolled_table(frame: &mut Frame, app: &mut App, area: Rect, table: Table, row_count: usize) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(area);
+
+    // One line goes to the header.
+    app.home_page_size = chunks[0].height.saturating_sub(1) as usize;
+    app.home_rows_area = Rect {
+        y: chunks[0].y + 1,
+        height: app.home_page_size as u16,
+        ..chunks[0]
+    };
+    let page_size = app.home_page_size;
+    let state = app.table_state();
+    frame.render_stateful_widget(table, chunks[0], state);
+
+    if row_count > page_size {
+        let mut scrollbar_state = ScrollbarState::new(row_count)
+            .viewport_content_length(page_size)
+            .position(state.selected().unwrap_or(0));
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight);
+        frame.render_stateful_widget(scrollbar, chunks[1], &mut scrollbar_state);
+    }
+}
+
+fn book_title(library: &Library, isbn: &Isbn) -> String {
+    library
+        .find(isbn)
+        .map_or(isbn.to_string(), |book| book.get_title().to_string())
+}
+
+/// The Loans tab: every book out on loan, soonest due first.
+fn loans_table(frame: &mut Frame, app: &mut App, area: Rect) {
+    let library = app.library.as_ref().unwrap();
+    let today = app.today();
+    let rows: Vec<Row> = library
+        .active_loans()
+        .into_iter()
+        .map(|loan| {
+            let style = match loan.is_overdue(today) {
+                true => app.theme.error,
+                false => app.theme.text,
+            };
+            Row::new(vec![
+                book_title(library, &loan.isbn),
+                loan.patron.clone(),
+                loan.checked_out.to_string(),
+                loan.due.to_string(),
+            ])
+            .style(style)
+        })
+        .collect();
+    let row_count = rows.len();
+    let header = Row::new(vec![t("Title"), t("Patron"), t("Checked out"), t("Due")])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let widths = [
+        Constraint::Fill(3),
+        Constraint::Fill(2),
+        Constraint::Length(11),
+        Constraint::Length(11),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .row_highlight_style(app.theme.selected)
+        .highlight_symbol("> ");
+    scrolled_table(frame, app, area, table, row_count);
+}
+
+/// The Patrons tab: everyone who has borrowed or reserved a book.
+fn patrons_table(frame: &mut Frame, app: &mut App, area: Rect) {
+    let library = app.library.as_ref().unwrap();
+    let today = app.today();
+    let rows: Vec<Row> = library
+        .patrons()
+        .into_iter()
+        .map(|patron| {
+            let overdue = patron
+                .on_loan
+                .iter()
+                .filter(|loan| loan.is_overdue(today))
+                .count();
+            let style = match overdue {
+                0 => app.theme.text,
+                _ => app.theme.error,
+            };
+            Row::new(vec![
+                patron.name.to_string(),
+                patron.on_loan.len().to_string(),
+                overdue.to_string(),
+                patron.holds.len().to_string(),
+                patron.loans.to_string(),
+            ])
+            .style(style)
+        })
+        .collect();
+    let row_count = rows.len();
+    let header = Row::new(vec![
+        t("Patron"),
+        t("On loan"),
+        t("Overdue"),
+        t("Holds"),
+        t("Borrowed"),
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD));
+    let widths = [
+        Constraint::Fill(1),
+        Constraint::Length(9),
+        Constraint::Length(9),
+        Constraint::Length(9),
+        Constraint::Length(9),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .row_highlight_style(app.theme.selected)
+        .highlight_symbol("> ");
+    scrolled_table(frame, app, area, table, row_count);
+}
+
+/// The selected patron's loans and holds.
+fn patron_detail_pane(frame: &mut Frame, app: &App, area: Rect) {
+    let library = app.library.as_ref().unwrap();
+    let today = app.today();
+    let patrons = library.patrons();
+    let mut lines = Vec::new();
+    match app.patrons_state.selected().and_then(|i| patrons.get(i)) {
+        Some(patron) => {
+            lines.push(field_line(t("Patron"), patron.name.to_string()));
+            lines.push(field_line(t("Borrowed"), patron.loans.to_string()));
+            lines.push(Line::default());
+            lines.push(field_line(t("On loan"), patron.on_loan.len().to_string()));
+            for loan in &patron.on_loan {
+                let style = match loan.is_overdue(today) {
+                    true => app.theme.error,
+                    false => app.theme.text,
+                };
+                lines.push(Line::styled(
+                    tr!("  {}, due {}", book_title(library, &loan.isbn), loan.due),
+                    style,
+                ));
+            }
+            lines.push(field_line(t("Holds"), patron.holds.len().to_string()));
+            for hold in &patron.holds {
+                lines.push(Line::from(tr!(
+                    "  {} (since {})",
+                    book_title(library, &hold.isbn),
+                    hold.placed
+                )));
+            }
+        }
+        None => lines.push(Line::from(t("No patron selected"))),
+    }
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().title(t("Details")).borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
+/// The Loans or Patrons tab: the table, with the selected row's details
+/// beside it when there's room.
+fn tab_content(frame: &mut Frame, app: &mut App, area: Rect) {
+    let (table_area, pane) = match Breakpoint::of(area) {
+        Breakpoint::Compact => (area, None),
+        _ => {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(40), Constraint::Length(48)])
+                .split(area);
+            (panes[0], Some(panes[1]))
+        }
+    };
+    match app.tab {
+        Tab::Loans => {
+            loans_table(frame, app, table_area);
+            if let Some(pane) = pane {
+                book_detail_pane(frame, app, pane);
+            }
+        }
+        Tab::Patrons => {
+            patrons_table(frame, app, table_area);
+            if let Some(pane) = pane {
+                patron_detail_pane(frame, app, pane);
+            }
+        }
+        Tab::Catalog | Tab::Reports => {}
+    }
+}
+
+fn field_line<'a>(name: &'a str, value: String) -> Line<'a> {
+    Line::from(vec![
+        Span::styled(
+            format!("{}: ", name),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(value),
+    ])
+}
+
+/// The full record of `book`: every field, its current loan, hold queue and
+/// a summary of its loan history.
+fn book_detail_lines<'a>(app: &App, book: &'a Book) -> Vec<Line<'a>> {
+    let library = app.library.as_ref().unwrap();
+    let today = app.today();
+
+    let mut lines = vec![
+        field_line(t("Title"), book.get_title().to_string()),
+        field_line(t("Author"), book.get_author().to_string()),
+        field_line(
+            t("ISBN"),
+            match book.get_isbn().to_isbn10() {
+                Ok(isbn10) => format!("{} (ISBN-10: {})", book.get_isbn(), isbn10),
+                Err(_) => book.get_isbn().to_string(),
+            },
+        ),
+        field_line(t("Year"), Column::Year.cell(book)),
+        field_line(t("Genre"), book.get_genre().to_string()),
+    ];
+    if let Some(series) = book.get_series() {
+        lines.push(field_line(t("Series"), series.to_string()));
+    }
+    if !book.get_tags().is_empty() {
+        lines.push(field_line(t("Tags"), book.get_tags().join(", ")));
+    }
+    lines.push(Line::from(vec![
+        Span::styled(
+            format!("{}: ", t("Status")),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            app.theme.status(book.get_status()),
+            app.theme.availability(book),
+        ),
+    ]));
+
+    lines.push(Line::default());
+    lines.push(match library.current_loan(book.get_isbn()) {
+        Some(loan) => {
+            let overdue = match loan.is_overdue(today) {
+                true => t(" (overdue)"),
+                false => "",
+            };
+            field_line(
+                t("Loan"),
+                tr!(
+                    "{} since {}, due {}{}",
+                    loan.patron,
+                    loan.checked_out,
+                    loan.due,
+                    overdue
+                ),
+            )
+        }
+        None => field_line(t("Loan"), t("not on loan").to_string()),
+    });
+
+    let holds = library.holds_for(book.get_isbn());
+    match holds.is_empty() {
+        true => lines.push(field_line(t("Holds"), t("none").to_string())),
+        false => {
+            lines.push(field_line(t("Holds"), tr!("{} waiting", holds.len())));
+            for (i, hold) in holds.iter().enumerate() {
+                lines.push(Line::from(tr!(
+                    "  {}. {} (since {})",
+                    i + 1,
+                    hold.patron,
+                    hold.placed
+                )));
+            }
+        }
+    }
+
+    let history = library.loan_history(book.get_isbn());
+    lines.push(field_line(
+        t("History"),
+        match history.last() {
+            Some(last) => tr!(
+                "borrowed {} time(s), last by {} on {}",
+                history.len(),
+                last.patron,
+                last.checked_out
+            ),
+            None => t("never borrowed").to_string(),
+        },
+    ));
+    lines
 }
 
-fn main_screen_content(frame: &mut Frame, app: &App, area: Rect) {
-    let mut list_items = Vec::<ListItem>::new();
+fn book_detail_pane(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default().title(t("Details")).borders(Borders::ALL);
+    let lines = match app.highlighted_book() {
+        Some(book) => book_detail_lines(app, book),
+        None => vec![Line::from(t("No book selected"))],
+    };
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
 
-    let books = app.library.as_ref().unwrap().get_books();
+/// Every book out on loan, soonest due first.
+fn loans_pane(frame: &mut Frame, app: &App, area: Rect) {
+    let library = app.library.as_ref().unwrap();
+    let today = app.today();
+    let loans = library.active_loans();
 
-    for book in books.iter() {
-        let item = ListItem::new(Line::from(Span::styled(
-            format!("{: <25} - {: <50}", book.get_author(), book.get_title()),
-            Style::default().fg(match book.get_available() {
-                true => Color::Green,
-                false => Color::Red,
-            }),
+    let mut lines = Vec::new();
+    for loan in &loans {
+        let title = book_title(library, &loan.isbn);
+        let style = match loan.is_overdue(today) {
+            true => app.theme.error,
+            false => app.theme.muted,
+        };
+        lines.push(Line::from(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(Span::styled(
+            tr!("  {}, due {}", loan.patron, loan.due),
+            style,
+        )));
+    }
+    if loans.is_empty() {
+        lines.push(Line::from(Span::styled(
+            t("No books on loan"),
+            app.theme.muted,
         )));
+    }
+    let paragraph = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(tr!("On Loan ({})", loans.len()))
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
+fn filter_bar(frame: &mut Frame, app: &App, area: Rect) {
+    let any = t("All").to_string();
+    let fields = [
+        (
+            FilterField::Genre,
+            t("Genre"),
+            app.filter
+                .genre
+                .as_ref()
+                .map_or(any.clone(), |g| g.to_string()),
+        ),
+        (
+            FilterField::Status,
+            t("Status"),
+            app.filter
+                .status
+                .as_ref()
+                .map_or(any.clone(), |s| s.to_string()),
+        ),
+        (
+            FilterField::YearFrom,
+            t("From"),
+            app.year_from_input.value().to_string(),
+        ),
+        (
+            FilterField::YearTo,
+            t("To"),
+            app.year_to_input.value().to_string(),
+        ),
+    ];
 
-        list_items.push(item);
+    // Narrow bars put the years on a line of their own.
+    let compact = Breakpoint::of(area) == Breakpoint::Compact;
+    let mut lines = Vec::<Line>::new();
+    let mut spans = Vec::<Span>::new();
+    let mut cursor = None;
+    for (field, name, value) in fields {
+        if compact && field == FilterField::YearFrom {
+            lines.push(Line::from(std::mem::take(&mut spans)));
+        }
+        let value = match field {
+            FilterField::Genre | FilterField::Status => format!("< {} >", value),
+            _ => format!("[{: <4}]", value),
+        };
+        let style = match app.filter_field == field {
+            true => app.theme.focus,
+            false => Style::default(),
+        };
+        spans.push(Span::raw(format!(" {}: ", name)));
+        if app.filter_field == field {
+            let input = match field {
+                FilterField::YearFrom => Some(&app.year_from_input),
+                FilterField::YearTo => Some(&app.year_to_input),
+                _ => None,
+            };
+            let before: usize = spans.iter().map(Span::width).sum();
+            let x = before as u16 + 1;
+            cursor = input.map(|input| (x + input.cursor_width(), lines.len() as u16));
+        }
+        spans.push(Span::styled(value, style));
+        spans.push(Span::raw("  "));
     }
+    lines.push(Line::from(spans));
 
-    let list = List::new(list_items);
-    frame.render_widget(list, area);
+    let bar =
+        Paragraph::new(lines).block(Block::default().title(t("Filters")).borders(Borders::ALL));
+    frame.render_widget(bar, area);
+    if let Some((x, y)) = cursor {
+        frame.set_cursor_position(Position::new(area.x + 1 + x, area.y + 1 + y));
+    }
 }
 
 fn checking_out_screen_content(frame: &mut Frame, app: &App, area: Rect) {
-    let book = app.selected_book.as_ref().unwrap();
-    let book_info = vec![
-        Line::from(Span::styled(
-            format!("Title: {}", book.get_title()),
-            Style::default().fg(Color::White),
-        )),
-        Line::from(Span::styled(
-            format!("Author: {}", book.get_author()),
-            Style::default().fg(Color::White),
-        )),
-        Line::from(Span::styled(
-            format!("ISBN: {}", book.get_isbn()),
-            Style::default().fg(Color::White),
-        )),
-    ];
+    // Read the book back from the library so the record reflects the latest
+    // checkout, return or hold.
+    let selected = app.selected_book.as_ref().unwrap();
+    let book = app
+        .library
+        .as_ref()
+        .unwrap()
+        .find(selected.get_isbn())
+        .unwrap_or(selected);
 
-    let book_info_paragraph = Paragraph::new(book_info)
+    let book_info_paragraph = Paragraph::new(book_detail_lines(app, book))
         .block(Block::default().borders(Borders::ALL))
-        .wrap(Wrap { trim: false })
-        .centered();
+        .wrap(Wrap { trim: false });
 
-    let new_area = centered_rect(60, 25, area);
+    let new_area = match Breakpoint::of(area) {
+        Breakpoint::Compact => area,
+        _ => centered_rect(70, 90, area),
+    };
 
     frame.render_widget(book_info_paragraph, new_area);
 }
 
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A bar per entry, labelled on the left, for labels too long to fit under
+/// a vertical bar.
+fn horizontal_bars<'a>(title: &'a str, bars: Vec<Bar<'a>>) -> BarChart<'a> {
+    BarChart::default()
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .direction(Direction::Horizontal)
+        .bar_width(1)
+        .bar_gap(0)
+        .data(BarGroup::default().bars(&bars))
+}
+
+fn bar(label: String, value: u64, style: Style) -> Bar<'static> {
+    Bar::default()
+        .label(Line::from(label))
+        .value(value)
+        .style(style)
+        .value_style(style.add_modifier(Modifier::REVERSED))
+}
+
+/// Charts about the collection: what it holds, where the books are and how
+/// much they're borrowed.
+fn dashboard_content(frame: &mut Frame, app: &App, area: Rect) {
+    let stats = Stats::new(app.library.as_ref().unwrap(), app.today());
+    let split = |area: Rect, direction: Direction, constraints: &[Constraint]| {
+        Layout::default()
+            .direction(direction)
+            .constraints(constraints.to_vec())
+            .split(area)
+    };
+    // Genres, status, decades, most borrowed and checkouts per month, in
+    // three rows of two when narrow.
+    let [genre_area, status_area, decade_area, borrowed_area, monthly_area] =
+        match Breakpoint::of(area) {
+            Breakpoint::Compact => {
+                let rows = split(area, Direction::Vertical, &[Constraint::Fill(1); 3]);
+                let top = split(rows[0], Direction::Horizontal, &[Constraint::Fill(1); 2]);
+                let middle = split(rows[1], Direction::Horizontal, &[Constraint::Fill(1); 2]);
+                [top[0], top[1], middle[0], middle[1], rows[2]]
+            }
+            _ => {
+                let rows = split(area, Direction::Vertical, &[Constraint::Fill(1); 2]);
+                let top = split(rows[0], Direction::Horizontal, &[Constraint::Fill(1); 3]);
+                let bottom = split(
+                    rows[1],
+                    Direction::Horizontal,
+                    &[Constraint::Fill(1), Constraint::Fill(2)],
+                );
+                [top[0], top[1], top[2], bottom[0], bottom[1]]
+            }
+        };
+
+    let genres = stats
+        .by_genre
+        .iter()
+        .map(|(genre, count)| bar(genre.to_string(), *count, app.theme.info))
+        .collect();
+    frame.render_widget(horizontal_bars(t("Books by genre"), genres), genre_area);
+
+    let status_rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .split(status_area);
+    let statuses = stats
+        .by_status
+        .iter()
+        .map(|(status, count)| {
+            let style = match status {
+                Status::Available => app.theme.available,
+                _ => app.theme.unavailable,
+            };
+            bar(status.to_string(), *count, style)
+        })
+        .collect();
+    frame.render_widget(
+        horizontal_bars(t("Books by status"), statuses),
+        status_rows[0],
+    );
+    let ratio = match stats.books {
+        0 => 0.0,
+        books => stats.available as f64 / books as f64,
+    };
+    let gauge = Gauge::default()
+        .block(Block::default().title(t("Available")).borders(Borders::ALL))
+        .gauge_style(app.theme.available)
+        .ratio(ratio)
+        .label(tr!("{} of {} available", stats.available, stats.books));
+    frame.render_widget(gauge, status_rows[1]);
+
+    let decades: Vec<Bar> = stats
+        .by_decade
+        .iter()
+        .map(|(decade, count)| bar(decade.to_string(), *count, app.theme.notice))
+        .collect();
+    let decade_chart = BarChart::default()
+        .block(
+            Block::default()
+                .title(t("Books by decade"))
+                .borders(Borders::ALL),
+        )
+        .bar_width(4)
+        .bar_gap(1)
+        .data(BarGroup::default().bars(&decades));
+    frame.render_widget(decade_chart, decade_area);
+
+    match stats.top_borrowed.is_empty() {
+        true => frame.render_widget(
+            Paragraph::new(t("No loans yet")).block(
+                Block::default()
+                    .title(t("Most borrowed"))
+                    .borders(Borders::ALL),
+            ),
+            borrowed_area,
+        ),
+        false => {
+            let borrowed = stats
+                .top_borrowed
+                .iter()
+                .map(|(title, count)| bar(title.clone(), *count, app.theme.hint))
+                .collect();
+            frame.render_widget(horizontal_bars(t("Most borrowed"), borrowed), borrowed_area);
+        }
+    }
+
+    let total: u64 = stats.monthly_checkouts.iter().map(|(_, count)| count).sum();
+    let block = Block::default()
+        .title(tr!("Checkouts per month ({} in total)", total))
+        .borders(Borders::ALL);
+    let inner = block.inner(monthly_area);
+    frame.render_widget(block, monthly_area);
+    let chart = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+    // Each month gets an equal slice of the width, so the line reads as bars.
+    let slice = (inner.width as usize / stats.monthly_checkouts.len().max(1)).max(1);
+    let data: Vec<u64> = stats
+        .monthly_checkouts
+        .iter()
+        .flat_map(|(_, count)| std::iter::repeat_n(*count, slice))
+        .collect();
+    frame.render_widget(
+        Sparkline::default().data(&data).style(app.theme.info),
+        chart[0],
+    );
+    let labels: String = stats
+        .monthly_checkouts
+        .iter()
+        .map(|(month, _)| {
+            let name = t(MONTHS[month.month0() as usize]);
+            format!("{: <1$}", name, slice)
+        })
+        .collect();
+    frame.render_widget(Span::styled(labels, app.theme.muted), chart[1]);
+}
+
+/// The footer summary of the Loans, Patrons and Reports tabs.
+fn tab_status(app: &App) -> Span<'static> {
+    let library = app.library.as_ref().unwrap();
+    let text = match app.tab {
+        Tab::Loans => {
+            let today = app.today();
+            let loans = library.active_loans();
+            let overdue = loans.iter().filter(|loan| loan.is_overdue(today)).count();
+            tr!("{} on loan, {} overdue", loans.len(), overdue)
+        }
+        Tab::Patrons => tr!("{} patrons", library.patrons().len()),
+        _ => {
+            let books = library.get_books();
+            let available = books.iter().filter(|book| book.get_available()).count();
+            tr!("{} books, {} available", books.len(), available)
+        }
+    };
+    Span::styled(text, app.theme.text)
+}
+
 fn main_screen_mode_footer(frame: &mut Frame, app: &App, area: Rect) {
-    let search_term = Text::from(vec![Line::from(vec![
-        "Query: ".into(),
-        app.searching_input.clone().into(),
-    ])]);
+    let search_prefix = match app.search_input_error() {
+        Some(_) => tr!("Invalid {} - Query: ", app.searching_criteria),
+        None => tr!("Searching by {} - Query: ", app.searching_criteria),
+    };
 
     let navigation_text = match app.current_screen {
-        CurrentScreen::Home => Span::styled("Home", Style::default().fg(Color::Green)),
-        CurrentScreen::Searching => Span::styled("Search", Style::default().fg(Color::Yellow)),
-        CurrentScreen::CheckingOut => Span::styled("Check Out", Style::default().fg(Color::Cyan)),
+        CurrentScreen::Home => Span::styled(app.tab.to_string(), app.theme.home_mode),
+        CurrentScreen::Searching => Span::styled(t("Search"), app.theme.search_mode),
+        CurrentScreen::Filtering => Span::styled(t("Filter"), app.theme.filter_mode),
+        CurrentScreen::CheckingOut => Span::styled(t("Check Out"), app.theme.book_mode),
         _ => Span::default(),
     };
 
     let status_text = match app.current_screen {
-        CurrentScreen::Home => Span::styled("OK", Style::default().fg(Color::DarkGray)),
-        CurrentScreen::Searching => match app.term_input_mode {
+        CurrentScreen::Home if app.tab != Tab::Catalog => tab_status(app),
+        CurrentScreen::Home => match app.column_mode {
             true => Span::styled(
-                format!("Searching by {} - {}", app.searching_criteria, search_term),
-                Style::default().fg(Color::White),
+                Column::ALL
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        let shown = match app.config.columns.contains(column) {
+                            true => "x",
+                            false => " ",
+                        };
+                        format!("{} [{}] {}", i + 1, shown, column)
+                    })
+                    .collect::<Vec<String>>()
+                    .join("  "),
+                app.theme.notice,
             ),
+            false => Span::styled(t("OK"), app.theme.muted),
+        },
+        CurrentScreen::Searching => match app.term_input_mode {
+            true => match app.search_input_error() {
+                Some(e) => Span::styled(
+                    format!("{}{}: {}", search_prefix, app.searching_input.value(), e),
+                    app.theme.error,
+                ),
+                None => Span::styled(
+                    format!("{}{}", search_prefix, app.searching_input.value()),
+                    app.theme.text,
+                ),
+            },
             false => Span::styled(
-                format!("Switching search criteria ({})", app.searching_criteria),
-                Style::default().fg(Color::Yellow),
+                tr!("Switching search criteria ({})", app.searching_criteria),
+                app.theme.notice,
             ),
         },
+        CurrentScreen::Filtering => Span::styled(
+            tr!(
+                "{} of {} books",
+                app.catalog_books().len(),
+                app.library.as_ref().unwrap().get_books().len()
+            ),
+            app.theme.text,
+        ),
         CurrentScreen::CheckingOut => {
             let book = app.selected_book.as_ref().unwrap();
             Span::styled(
-                format!(
-                    "Checking out '{}', by {}",
+                tr!(
+                    "Checking out '{}', by {}, for {}",
                     book.get_title(),
-                    book.get_author()
+                    book.get_author(),
+                    app.patron()
                 ),
-                Style::default().fg(Color::LightBlue),
+                app.theme.info,
             )
         }
         _ => Span::default(),
     };
-    let current_navigation_text = vec![
+    let status_offset = navigation_text.width() + 3;
+    let mut current_navigation_text = vec![
         navigation_text,
-        Span::styled(" | ", Style::default().fg(Color::White)),
+        Span::styled(" | ", app.theme.text),
         status_text,
     ];
+    if app.filter.is_active() {
+        current_navigation_text.push(Span::styled(" | ", app.theme.text));
+        current_navigation_text.push(Span::styled(
+            tr!("Filters: {}", app.filter),
+            app.theme.filters,
+        ));
+    }
 
     let mode_footer = Paragraph::new(Line::from(current_navigation_text))
         .block(Block::default().borders(Borders::ALL));
 
     frame.render_widget(mode_footer, area);
+
+    if let (CurrentScreen::Searching, true) = (&app.current_screen, app.term_input_mode) {
+        let x = status_offset + search_prefix.width();
+        frame.set_cursor_position(Position::new(
+            area.x + 1 + x as u16 + app.searching_input.cursor_width(),
+            area.y + 1,
+        ));
+    }
 }
 
-fn main_screen_key_hints(frame: &mut Frame, app: &App, area: Rect) {
-    let mut keys = Vec::<String>::new();
-    match app.current_screen {
-        CurrentScreen::Home => {
-            keys.push("(s) to search".into());
-            keys.push("(q) to quit".into());
-        }
-        CurrentScreen::Searching => {
-            if app.term_input_mode {
-                keys.push("Type to search".into());
-                keys.push("(tab) to switch to search criteria selection".into());
-                keys.push("(enter) to check out".into());
-                keys.push("(esc) main screen".into());
-            } else {
-                keys.push("(a) by Author".into());
-                keys.push("(t) by Title".into());
-                keys.push("(i) by ISBN".into());
-                keys.push("(tab) to switch to query input".into());
-                keys.push("(esc) main screen".into());
-                keys.push("(q) to quit".into());
+/// The hints shown for each key context: the actions whose keys are listed
+/// and what they do. Hints without actions are shown as they are.
+fn key_hints(context: KeyContext) -> Vec<(&'static [Action], &'static str)> {
+    use Action::*;
+    match context {
+        KeyContext::Home => vec![
+            (&[MoveUp, MoveDown], "to move"),
+            (&[Confirm], "to open"),
+            (&[], "(1-6) to sort"),
+            (&[Columns], "columns"),
+            (&[Filter], "to filter"),
+            (&[Search], "to search"),
+            (&[NextTab, PrevTab], "tabs"),
+            (&[Quit], "to quit"),
+            (&[Help], "help"),
+            (&[Palette], "commands"),
+        ],
+        KeyContext::Columns => vec![
+            (&[], "(1-6) to show/hide a column"),
+            (&[Columns, Back], "done"),
+        ],
+        KeyContext::SearchInput => vec![
+            (&[], "Type to search"),
+            (&[MoveUp, MoveDown], "to pick a result"),
+            (
+                &[ToggleSearchInput],
+                "to switch to search criteria selection",
+            ),
+            (&[Confirm], "to open"),
+            (&[Back], "main screen"),
+        ],
+        KeyContext::SearchCriteria => vec![
+            (&[SearchByAuthor], "by Author"),
+            (&[SearchByTitle], "by Title"),
+            (&[SearchByIsbn], "by ISBN"),
+            (&[ToggleSearchInput], "to switch to query input"),
+            (&[Back], "main screen"),
+            (&[Quit], "to quit"),
+            (&[Help], "help"),
+        ],
+        KeyContext::Filtering => vec![
+            (&[NextField, PrevField], "to pick a filter"),
+            (&[PrevValue, NextValue], "to change it"),
+            (&[], "(0-9) to type a year"),
+            (&[ClearFilter], "to clear all"),
+            (&[Confirm, Back], "done"),
+            (&[Help], "help"),
+        ],
+        KeyContext::Loans => vec![
+            (&[MoveUp, MoveDown], "to move"),
+            (&[Confirm], "to open"),
+            (&[ReturnBook], "to return"),
+            (&[NextTab, PrevTab], "tabs"),
+            (&[Back], "catalog"),
+            (&[Quit], "to quit"),
+            (&[Help], "help"),
+        ],
+        KeyContext::Patrons => vec![
+            (&[MoveUp, MoveDown], "to move"),
+            (&[Confirm], "to choose"),
+            (&[NextTab, PrevTab], "tabs"),
+            (&[Back], "catalog"),
+            (&[Quit], "to quit"),
+            (&[Help], "help"),
+        ],
+        KeyContext::Reports => vec![
+            (&[NextTab, PrevTab], "tabs"),
+            (&[Dashboard, Back], "catalog"),
+            (&[Quit], "to quit"),
+            (&[Help], "help"),
+            (&[Palette], "commands"),
+        ],
+        KeyContext::Book => vec![
+            (&[Confirm], "to check out book"),
+            (&[ReturnBook], "to return"),
+            (&[PlaceHold], "to place a hold"),
+            (&[BackToSearch], "to go back"),
+            (&[Back], "main screen"),
+            (&[Messages], "messages"),
+            (&[Quit], "to quit"),
+            (&[Help], "help"),
+        ],
+        _ => vec![],
+    }
+}
+
+fn main_screen_key_hints(frame: &mut Frame, app: &mut App, area: Rect) {
+    let style = app.theme.hint;
+    let hints = key_hints(app.key_context())
+        .into_iter()
+        .filter_map(|(actions, label)| {
+            if actions.is_empty() {
+                return Some((Span::styled(t(label), style), None));
+            }
+            let keys: Vec<Key> = actions
+                .iter()
+                .filter_map(|action| app.keymap.key(*action))
+                .collect();
+            let first = *keys.first()?;
+            let names: Vec<String> = keys.iter().map(Key::symbol).collect();
+            let hint = format!("({}) {}", names.join("/"), t(label));
+            Some((Span::styled(hint, style), Some(first)))
+        })
+        .collect();
+    clickable_line(frame, app, area, hints, Span::styled(" - ", style));
+}
+
+/// Keys that aren't actions, so they can't be rebound.
+fn fixed_keys(context: KeyContext) -> Vec<(&'static str, &'static str, &'static str)> {
+    let mut keys = match context {
+        KeyContext::Home => vec![("Actions", "1-6", "Sort by a column, again to reverse")],
+        KeyContext::Columns => vec![("Actions", "1-6", "Show or hide a column")],
+        KeyContext::Filtering => vec![("Editing", "0-9", "Type a year")],
+        _ => vec![],
+    };
+    if let KeyContext::NewOwner | KeyContext::SearchInput | KeyContext::Filtering = context {
+        keys.extend([
+            ("Editing", "←/→", "Move the cursor"),
+            ("Editing", "ctrl+←/→, alt+b/f", "Move by word"),
+            ("Editing", "home/end, ctrl+a/e", "Go to the start or end"),
+            ("Editing", "backspace/delete", "Delete a character"),
+            ("Editing", "ctrl+w", "Delete the previous word"),
+            ("Editing", "ctrl+u/k", "Delete to the start or end"),
+        ]);
+    }
+    keys
+}
+
+fn context_title(context: KeyContext) -> &'static str {
+    match context {
+        KeyContext::Loading => t("Loading"),
+        KeyContext::NewOwner => t("New Library"),
+        KeyContext::Home => t("Home"),
+        KeyContext::Columns => t("Columns"),
+        KeyContext::SearchInput => t("Search"),
+        KeyContext::SearchCriteria => t("Search Criteria"),
+        KeyContext::Filtering => t("Filter"),
+        KeyContext::Book => t("Check Out"),
+        KeyContext::Exiting => t("Exit"),
+        KeyContext::Loans => t("Loans"),
+        KeyContext::Patrons => t("Patrons"),
+        KeyContext::Reports => t("Reports"),
+        KeyContext::Help => t("Help"),
+        KeyContext::Palette => t("Command Palette"),
+        KeyContext::Messages => t("Messages"),
+    }
+}
+
+/// Every binding for the current screen, grouped, over whatever is shown.
+fn help_overlay(frame: &mut Frame, app: &mut App) {
+    let context = app.screen_context();
+    let mut entries: Vec<(&str, String, &str)> = context
+        .actions()
+        .iter()
+        .filter_map(|action| {
+            let keys = app.keymap.keys(*action);
+            if keys.is_empty() {
+                return None;
             }
+            let (group, description) = describe(context, *action);
+            let keys: Vec<String> = keys.iter().map(Key::symbol).collect();
+            Some((group, keys.join(", "), t(description)))
+        })
+        .collect();
+    entries.extend(
+        fixed_keys(context)
+            .into_iter()
+            .map(|(group, keys, description)| (group, keys.to_string(), t(description))),
+    );
+
+    let mut rows = Vec::new();
+    for group in ["Navigation", "Actions", "Editing", "General"] {
+        let in_group: Vec<_> = entries.iter().filter(|(g, _, _)| *g == group).collect();
+        if in_group.is_empty() {
+            continue;
         }
-        CurrentScreen::CheckingOut => {
-            keys.push("(enter) to check out book".into());
-            keys.push("(b) to go back".into());
-            keys.push("(esc) main screen".into());
-            keys.push("(q) to quit".into());
+        if !rows.is_empty() {
+            rows.push(Row::new(vec![""]));
+        }
+        rows.push(Row::new(vec![Cell::from(t(group))]).style(app.theme.notice));
+        for (_, keys, description) in in_group {
+            rows.push(Row::new(vec![keys.clone(), description.to_string()]));
         }
-        _ => {}
     }
-    let current_keys_hint = Span::styled(keys.join(" - "), Style::default().fg(Color::Green));
 
-    let key_notes_footer = Paragraph::new(Line::from(current_keys_hint))
-        .block(Block::default().borders(Borders::NONE))
-        .centered();
+    let area = centered_rect(70, 80, frame.area());
+    frame.render_widget(Clear, area);
+    let close: Vec<String> = [Action::Help, Action::Back]
+        .iter()
+        .filter_map(|action| app.keymap.key(*action))
+        .map(|key| key.symbol())
+        .collect();
+    let block = Block::default()
+        .title(tr!("Help - {}", context_title(context)))
+        .title_bottom(Line::from(tr!("({}) close", close.join("/"))).right_aligned())
+        .borders(Borders::ALL)
+        .padding(Padding::horizontal(1));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let page = inner.height as usize;
+    let row_count = rows.len();
+    app.help_page_size = page;
+    app.help_scroll = app.help_scroll.min(row_count.saturating_sub(page));
+    let visible: Vec<Row> = rows.into_iter().skip(app.help_scroll).collect();
+    let table = Table::new(visible, [Constraint::Length(22), Constraint::Fill(1)]);
+    frame.render_widget(table, inner);
+
+    if row_count > page {
+        let mut scrollbar_state = ScrollbarState::new(row_count.saturating_sub(page))
+            .viewport_content_length(page)
+            .position(app.help_scroll);
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            area.inner(Margin::new(0, 1)),
+            &mut scrollbar_state,
+        );
+    }
+}
+
+/// The command palette: a query line over the matching commands, or the
+/// argument prompt once a command that needs one is chosen.
+fn palette_overlay(frame: &mut Frame, app: &mut App) {
+    let matches = app.palette_matches();
+    let palette = app.palette.as_ref().unwrap();
+    let full = frame.area();
+    let width = (full.width * 3 / 5).max(40).min(full.width);
+    let height = match palette.pending {
+        Some(_) => 4,
+        None => (matches.len() as u16 + 4).min(full.height * 3 / 5).max(5),
+    };
+    let area = Rect::new(
+        full.x + (full.width - width) / 2,
+        full.y + full.height / 6,
+        width,
+        height.min(full.height),
+    );
+    frame.render_widget(Clear, area);
+
+    let (title, input) = match &palette.pending {
+        Some((_, label, argument)) => (*label, argument),
+        None => (t("Command Palette"), &palette.query),
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .padding(Padding::horizontal(1));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)])
+        .split(inner);
+    let prompt = Rect {
+        width: 2,
+        ..rows[0]
+    };
+    frame.render_widget(Span::styled("> ", app.theme.notice), prompt);
+    let field = Rect {
+        x: rows[0].x + 2,
+        width: rows[0].width.saturating_sub(2),
+        height: 1,
+        ..rows[0]
+    };
+    input.render(frame, field, app.theme.text, true);
+
+    if palette.pending.is_some() {
+        return;
+    }
+    let visible = rows[1].height as usize;
+    let selected = palette.selected.min(matches.len().saturating_sub(1));
+    let offset = (selected + 1).saturating_sub(visible);
+    let context = app.screen_context();
+    let table_rows: Vec<Row> = matches
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .map(|(i, (command, title))| {
+            let key = match command {
+                Command::Run(action) if context.actions().contains(action) => app
+                    .keymap
+                    .key(*action)
+                    .map_or(String::new(), |key| key.symbol()),
+                _ => String::new(),
+            };
+            let style = match i == selected {
+                true => app.theme.selected,
+                false => app.theme.text,
+            };
+            Row::new(vec![
+                Cell::from(title.clone()),
+                Cell::from(key).style(app.theme.muted),
+            ])
+            .style(style)
+        })
+        .collect();
+    let table = Table::new(table_rows, [Constraint::Fill(1), Constraint::Length(10)]);
+    frame.render_widget(table, rows[1]);
+}
+
+fn level_style(app: &App, level: Level) -> Style {
+    match level {
+        Level::Info => app.theme.info,
+        Level::Warning => app.theme.notice,
+        Level::Error => app.theme.error,
+    }
+}
+
+/// The messages so far, newest first.
+fn history_overlay(frame: &mut Frame, app: &mut App) {
+    let rows: Vec<Row> = app
+        .notifications
+        .history()
+        .iter()
+        .rev()
+        .map(|notification| {
+            Row::new(vec![
+                Cell::from(notification.time.format("%H:%M:%S").to_string()),
+                Cell::from(notification.level.to_string())
+                    .style(level_style(app, notification.level)),
+                Cell::from(notification.text.clone()),
+            ])
+        })
+        .collect();
 
-    frame.render_widget(key_notes_footer, area);
+    let area = centered_rect(70, 80, frame.area());
+    frame.render_widget(Clear, area);
+    let close: Vec<String> = [Action::Messages, Action::Back]
+        .iter()
+        .filter_map(|action| app.keymap.key(*action))
+        .map(|key| key.symbol())
+        .collect();
+    let block = Block::default()
+        .title(t("Messages"))
+        .title_bottom(Line::from(tr!("({}) close", close.join("/"))).right_aligned())
+        .borders(Borders::ALL)
+        .padding(Padding::horizontal(1));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if rows.is_empty() {
+        frame.render_widget(
+            Paragraph::new(t("No messages yet")).style(app.theme.muted),
+            inner,
+        );
+        return;
+    }
+    let page = inner.height as usize;
+    let row_count = rows.len();
+    app.history_page_size = page;
+    app.history_scroll = app.history_scroll.min(row_count.saturating_sub(page));
+    let visible: Vec<Row> = rows.into_iter().skip(app.history_scroll).collect();
+    let table = Table::new(
+        visible,
+        [
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+        ],
+    );
+    frame.render_widget(table, inner);
+
+    if row_count > page {
+        let mut scrollbar_state = ScrollbarState::new(row_count.saturating_sub(page))
+            .viewport_content_length(page)
+            .position(app.history_scroll);
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            area.inner(Margin::new(0, 1)),
+            &mut scrollbar_state,
+        );
+    }
 }
 
-fn main_screen(frame: &mut Frame, app: &App) {
+/// The current toasts, stacked in the top right corner below the title bar.
+fn toasts(frame: &mut Frame, app: &App) {
+    let full = frame.area();
+    let max_width = TOAST_WIDTH.min(full.width.saturating_sub(2));
+    let width = app
+        .notifications
+        .toasts()
+        .map(|notification| notification.text.width() as u16 + 4)
+        .max()
+        .unwrap_or(0)
+        .clamp(20, max_width);
+    let mut y = full.y + 3;
+    for notification in app.notifications.toasts() {
+        let style = level_style(app, notification.level);
+        let lines = (notification.text.width() as u16)
+            .div_ceil(width - 4)
+            .clamp(1, TOAST_LINES);
+        let height = lines + 2;
+        if y + height > full.bottom() {
+            break;
+        }
+        let area = Rect::new(full.right() - width - 1, y, width, height);
+        let toast = Paragraph::new(notification.text.as_str())
+            .style(app.theme.text)
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .title(Span::styled(notification.level.to_string(), style))
+                    .borders(Borders::ALL)
+                    .border_style(style)
+                    .padding(Padding::horizontal(1)),
+            );
+        frame.render_widget(Clear, area);
+        frame.render_widget(toast, area);
+        y += height;
+    }
+}
+
+fn main_screen(frame: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -241,7 +1564,48 @@ fn main_screen(frame: &mut Frame, app: &App) {
 
     match app.current_screen {
         CurrentScreen::CheckingOut => checking_out_screen_content(frame, app, chunks[1]),
-        _ => main_screen_content(frame, app, chunks[1]),
+        CurrentScreen::Home if app.tab == Tab::Reports => dashboard_content(frame, app, chunks[1]),
+        CurrentScreen::Home if app.tab != Tab::Catalog => tab_content(frame, app, chunks[1]),
+        _ => {
+            let mut content = chunks[1];
+            let breakpoint = Breakpoint::of(content);
+            if let CurrentScreen::Filtering = app.current_screen {
+                let height = match breakpoint {
+                    Breakpoint::Compact => 4,
+                    _ => 3,
+                };
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(height), Constraint::Min(1)])
+                    .split(content);
+                filter_bar(frame, app, rows[0]);
+                content = rows[1];
+            }
+            match breakpoint {
+                Breakpoint::Compact => main_screen_content(frame, app, content),
+                Breakpoint::Normal => {
+                    let panes = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Min(40), Constraint::Length(48)])
+                        .split(content);
+                    main_screen_content(frame, app, panes[0]);
+                    book_detail_pane(frame, app, panes[1]);
+                }
+                Breakpoint::Wide => {
+                    let panes = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([
+                            Constraint::Min(40),
+                            Constraint::Length(48),
+                            Constraint::Length(40),
+                        ])
+                        .split(content);
+                    main_screen_content(frame, app, panes[0]);
+                    book_detail_pane(frame, app, panes[1]);
+                    loans_pane(frame, app, panes[2]);
+                }
+            }
+        }
     }
 
     let footer_chunks = Layout::default()
@@ -254,12 +1618,61 @@ fn main_screen(frame: &mut Frame, app: &App) {
     main_screen_key_hints(frame, app, footer_chunks[1]);
 }
 
-pub fn ui(frame: &mut Frame, app: &App) {
+/// Shown instead of any screen when the terminal can't fit one.
+fn too_small_screen(frame: &mut Frame) {
+    let area = frame.area();
+    let message = tr!(
+        "Terminal too small\n{}x{}, needs {}x{}",
+        area.width,
+        area.height,
+        MIN_WIDTH,
+        MIN_HEIGHT
+    );
+    let lines = Text::from(message);
+    let top = area.height.saturating_sub(lines.height() as u16) / 2;
+    let area = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    frame.render_widget(
+        Paragraph::new(lines).centered().wrap(Wrap { trim: true }),
+        area,
+    );
+}
+
+pub fn ui(frame: &mut Frame, app: &mut App) {
+    app.click_targets.clear();
+    app.tab_areas.clear();
+    app.home_rows_area = Rect::default();
+    let area = frame.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        too_small_screen(frame);
+        return;
+    }
     match app.current_screen {
-        CurrentScreen::Loading => loading_screen(frame),
+        CurrentScreen::Loading => loading_screen(frame, app),
         CurrentScreen::NewOwner => new_owner_screen(frame, app),
-        CurrentScreen::CheckedOutResult => checked_out_result_screen(frame, app),
-        CurrentScreen::Exiting => exiting_screen(frame),
+        CurrentScreen::Exiting => exiting_screen(frame, app),
         _ => main_screen(frame, app),
     }
+    if app.help_open || app.history_open || app.palette.is_some() {
+        // Clicks on the screen below would act on it, not the overlay.
+        app.click_targets.clear();
+        app.tab_areas.clear();
+        app.home_rows_area = Rect::default();
+    }
+    if app.palette.is_some() {
+        palette_overlay(frame, app);
+    }
+    if app.history_open {
+        history_overlay(frame, app);
+    }
+    if app.help_open {
+        help_overlay(frame, app);
+    }
+    // The history lists the toasts already.
+    if !app.history_open {
+        toasts(frame, app);
+    }
 }
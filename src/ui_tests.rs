@@ -0,0 +1,235 @@
+//! Snapshot tests for the UI: each test puts the app in some state, draws it
+//! on a `TestBackend` and compares the screen with the snapshot stored in
+//! `src/snapshots`. After an intended change, review the new snapshots with
+//! `cargo insta review`.
+
+use chrono::{Local, TimeZone};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use insta::assert_snapshot;
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+
+use crate::app::{App, CurrentScreen};
+use crate::config::Config;
+use crate::i18n::Language;
+use crate::keymap::Action;
+use crate::notifications::Level;
+use crate::theme::{Theme, ThemeName};
+use crate::ui::ui;
+
+/// Six books, two of them on loan (one overdue), one hold and one lost book.
+const LIBRARY: &str = include_str!("testdata/library.json");
+
+/// An app with the default settings in English and colour, at 09:30 on
+/// 14 March 2024, before the library is loaded.
+fn app() -> App {
+    let config = Config {
+        language: Some(Language::English),
+        ..Config::default()
+    };
+    let mut app = App::with_config(config);
+    // Not `Theme::load`, which would follow `NO_COLOR`.
+    app.theme = Theme::new(ThemeName::Default);
+    app.clock = Some(Local.with_ymd_and_hms(2024, 3, 14, 9, 30, 0).unwrap());
+    app
+}
+
+/// Like `app`, showing the test library's catalog.
+fn loaded() -> App {
+    let mut app = app();
+    app.set_library(Some(serde_json::from_str(LIBRARY).unwrap()));
+    app.loaded = true;
+    app.current_screen = CurrentScreen::Home;
+    app
+}
+
+fn perform(app: &mut App, actions: &[Action]) {
+    for action in actions {
+        app.update(*action);
+    }
+}
+
+fn type_text(app: &mut App, text: &str) {
+    for c in text.chars() {
+        app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+}
+
+fn render(app: &mut App, width: u16, height: u16) -> TestBackend {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|frame| ui(frame, app)).unwrap();
+    terminal.backend().clone()
+}
+
+#[test]
+fn loading() {
+    assert_snapshot!(render(&mut app(), 100, 30));
+}
+
+#[test]
+fn new_owner() {
+    let mut app = app();
+    app.entering_owner = true;
+    app.current_screen = CurrentScreen::NewOwner;
+    type_text(&mut app, "Ana");
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn catalog() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::MoveDown, Action::MoveDown]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn catalog_narrow() {
+    assert_snapshot!(render(&mut loaded(), 60, 20));
+}
+
+#[test]
+fn catalog_too_small() {
+    assert_snapshot!(render(&mut loaded(), 30, 8));
+}
+
+#[test]
+fn catalog_sorted() {
+    let mut app = loaded();
+    // Descending by title.
+    type_text(&mut app, "11");
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn choosing_columns() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Columns]);
+    type_text(&mut app, "4");
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn searching() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Search]);
+    type_text(&mut app, "the");
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+/// Searching by ISBN for `isbn`.
+fn searching_isbn(isbn: &str) -> App {
+    let mut app = loaded();
+    perform(
+        &mut app,
+        &[
+            Action::Search,
+            Action::ToggleSearchInput,
+            Action::SearchByIsbn,
+            Action::ToggleSearchInput,
+        ],
+    );
+    type_text(&mut app, isbn);
+    app
+}
+
+#[test]
+fn searching_partial_isbn() {
+    let mut app = searching_isbn("978044");
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn searching_isbn_with_wrong_check_digit() {
+    let mut app = searching_isbn("9780441172710");
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn filtering() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Filter, Action::NextValue]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn checking_out_available_book() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::MoveDown, Action::Confirm]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn checking_out_borrowed_book() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Confirm]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn exiting() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Quit]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn loans_tab() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::NextTab]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn patrons_tab() {
+    let mut app = loaded();
+    perform(
+        &mut app,
+        &[Action::NextTab, Action::NextTab, Action::MoveDown],
+    );
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn reports_tab() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Dashboard]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn help() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Help]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn palette() {
+    let mut app = loaded();
+    perform(&mut app, &[Action::Palette]);
+    type_text(&mut app, "exp");
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn toasts() {
+    let mut app = loaded();
+    app.notify(Level::Info, "Checked out \"Dune\"".to_string());
+    app.notify(Level::Warning, "Book is not available!".to_string());
+    app.notify(Level::Error, "Couldn't save library: disk full".to_string());
+    assert_snapshot!(render(&mut app, 100, 30));
+}
+
+#[test]
+fn message_history() {
+    let mut app = loaded();
+    for i in 1..=4 {
+        app.notify(Level::Info, format!("Message {i}"));
+    }
+    app.notify(
+        Level::Error,
+        "Couldn't export: permission denied".to_string(),
+    );
+    perform(&mut app, &[Action::Messages]);
+    assert_snapshot!(render(&mut app, 100, 30));
+}